@@ -0,0 +1,92 @@
+//! Datasheet accuracy bounds for compensated values.  Bounds are given in
+//! the same units as the readings (Fahrenheit, InHg and %RH).  Where the
+//! datasheet specifies wider bounds for an extended temperature range,
+//! those are used; beyond every range it covers, the datasheet makes no
+//! promise and the bound is left unspecified.
+
+use super::measurement::{Measurement, FAHRENHEIT_PER_CELSIUS, IN_HG_PER_PASCAL};
+
+/// Error bars for a single value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accuracy {
+    /// Bound on the difference from the true value, or `None` where the
+    /// datasheet specifies none.
+    pub absolute: Option<f64>,
+    /// Bound on the error of a change between two readings, where the
+    /// datasheet specifies one.
+    pub relative: Option<f64>,
+    /// False when the value lies outside the range of the datasheet's
+    /// headline bounds, even if an extended-range bound still applies.
+    pub in_spec: bool,
+}
+
+/// Error bars for each channel.  A channel is `None` when it has no value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeasurementAccuracy {
+    pub temperature: Option<Accuracy>,
+    pub pressure: Option<Accuracy>,
+    pub humidity: Option<Accuracy>,
+}
+
+/// Absolute temperature accuracy in Celsius, by (min, max) temperature.
+const TEMPERATURE_BOUNDS: [(f64, f64, f64); 3] = [(0.0, 65.0, 1.0),
+                                                  (-20.0, 0.0, 1.25),
+                                                  (-40.0, -20.0, 1.5)];
+
+/// Absolute pressure accuracy in hPa, by (min, max) temperature, for
+/// pressures of 300 to 1100 hPa.
+const PRESSURE_BOUNDS: [(f64, f64, f64); 2] = [(0.0, 65.0, 1.0), (-20.0, 0.0, 1.7)];
+
+/// Computes the accuracy of each value in `measurement`.
+pub fn accuracy(measurement: &Measurement) -> MeasurementAccuracy {
+    let celsius = measurement.temperature.map(|f| (f - 32.0) / FAHRENHEIT_PER_CELSIUS);
+    let hpa = measurement.pressure.map(|p| p / IN_HG_PER_PASCAL / 100.0);
+
+    MeasurementAccuracy {
+        temperature: celsius.map(|t| {
+            Accuracy {
+                absolute: lookup(&TEMPERATURE_BOUNDS, t).map(|c| c * FAHRENHEIT_PER_CELSIUS),
+                relative: None,
+                in_spec: in_range(t, 0.0, 65.0),
+            }
+        }),
+        pressure: hpa.map(|p| {
+            // The relative bound has its own, narrower window:
+            let relative_in_spec = in_range(p, 700.0, 900.0) &&
+                                   celsius.is_some_and(|t| in_range(t, 25.0, 40.0));
+            let absolute = match celsius {
+                Some(t) if in_range(p, 300.0, 1100.0) => lookup(&PRESSURE_BOUNDS, t),
+                _ => None,
+            };
+            Accuracy {
+                absolute: absolute.map(hpa_to_in_hg),
+                relative: if relative_in_spec { Some(hpa_to_in_hg(0.12)) } else { None },
+                in_spec: in_range(p, 300.0, 1100.0) &&
+                         celsius.is_some_and(|t| in_range(t, 0.0, 65.0)),
+            }
+        }),
+        humidity: measurement.humidity.map(|h| {
+            let in_spec = in_range(h, 20.0, 80.0);
+            Accuracy {
+                absolute: if in_spec { Some(3.0) } else { None },
+                relative: None,
+                in_spec,
+            }
+        }),
+    }
+}
+
+/// Returns the bound of the first row whose temperature range holds `celsius`.
+fn lookup(bounds: &[(f64, f64, f64)], celsius: f64) -> Option<f64> {
+    bounds.iter()
+        .find(|&&(min, max, _)| in_range(celsius, min, max))
+        .map(|&(_, _, bound)| bound)
+}
+
+fn in_range(value: f64, min: f64, max: f64) -> bool {
+    value >= min && value <= max
+}
+
+fn hpa_to_in_hg(hpa: f64) -> f64 {
+    hpa * 100.0 * IN_HG_PER_PASCAL
+}
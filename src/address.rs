@@ -0,0 +1,56 @@
+//! The BME280's two possible I2C addresses.
+
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+
+/// I2C address of a BME280, selected by the level of its SDO pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bme280Address {
+    /// 0x76, with SDO tied to GND.
+    Primary = 0x76,
+    /// 0x77, with SDO tied to VDDIO.
+    Secondary = 0x77,
+}
+
+impl Bme280Address {
+    /// Every address a BME280 can answer on.
+    pub const ALL: [Bme280Address; 2] = [Bme280Address::Primary, Bme280Address::Secondary];
+
+    /// Returns the address selected by the given SDO pin level.
+    pub fn from_sdo(sdo_high: bool) -> Bme280Address {
+        if sdo_high {
+            Bme280Address::Secondary
+        } else {
+            Bme280Address::Primary
+        }
+    }
+
+    pub fn value(self) -> u16 {
+        self as u16
+    }
+}
+
+impl From<Bme280Address> for u16 {
+    fn from(address: Bme280Address) -> u16 {
+        address.value()
+    }
+}
+
+impl TryFrom<u16> for Bme280Address {
+    /// The address a BME280 cannot have.
+    type Error = u16;
+
+    fn try_from(address: u16) -> Result<Bme280Address, u16> {
+        match address {
+            0x76 => Ok(Bme280Address::Primary),
+            0x77 => Ok(Bme280Address::Secondary),
+            _ => Err(address),
+        }
+    }
+}
+
+impl Display for Bme280Address {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:#04x}", self.value())
+    }
+}
@@ -0,0 +1,69 @@
+//! Command line tool for the BME280 crate.
+//!
+//! Usage:
+//!
+//! ```text
+//! bme280 driver <status|instantiate|release|bind|delete> <bus> <address> [driver] [--sysfs-root DIR]
+//! ```
+
+extern crate bme280;
+
+use std::convert::TryFrom;
+use std::env;
+use std::process;
+
+use bme280::address::Bme280Address;
+use bme280::driver::DriverControl;
+
+const USAGE: &str = "usage: bme280 driver <status|instantiate|release|bind|delete> <bus> <address> \
+                     [driver] [--sysfs-root DIR]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(message) = run(&args) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("driver") => driver(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn driver(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut control = DriverControl::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--sysfs-root" {
+            let root = try!(iter.next().ok_or_else(|| USAGE.to_string()));
+            control = DriverControl::new(root);
+        } else {
+            positional.push(arg.as_str());
+        }
+    }
+    if positional.len() < 3 {
+        return Err(USAGE.to_string());
+    }
+    let bus = try!(positional[1].parse().map_err(|_| format!("invalid bus: {}", positional[1])));
+    let address = try!(parse_address(positional[2]));
+
+    let result = match (positional[0], positional.get(3)) {
+        ("status", None) => control.binding(bus, address).map(|binding| println!("{}", binding)),
+        ("instantiate", None) => control.instantiate(bus, address),
+        ("release", None) => control.release(bus, address),
+        ("bind", Some(driver)) => control.bind(bus, address, driver),
+        ("delete", None) => control.delete(bus, address),
+        _ => return Err(USAGE.to_string()),
+    };
+    result.map_err(|err| err.to_string())
+}
+
+fn parse_address(text: &str) -> Result<Bme280Address, String> {
+    let digits = text.trim_start_matches("0x");
+    let value = try!(u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address: {}", text)));
+    Bme280Address::try_from(value).map_err(|value| format!("{:#04x} is not a BME280 address", value))
+}
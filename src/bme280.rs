@@ -3,37 +3,113 @@
 //! Intended to provide a simplified abstraction for communicating with the Bosch BME280
 //! sensor using an I2C bus in Linux
 
-use std::{thread, time};
+use std::thread;
 use i2cdev::core::I2CDevice;
 use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::ops::DerefMut;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 
+use super::accuracy;
+use super::address::Bme280Address;
+use super::bus;
 use super::calibration::Calibration;
-use super::register::Register;
+use super::error::Bme280Error;
+use super::health::{FlatlineDetector, Health};
+use super::lock::BusLock;
+use super::measurement::{Channel, Measurement, FAHRENHEIT_PER_CELSIUS, IN_HG_PER_PASCAL};
+use super::precision::{self, Precision};
+use super::plausibility::{self, Plausibility};
+use super::preset::Preset;
+use super::retry::{self, RetryCounters, RetryPolicy};
+use super::timing::{self, MeasurementTime};
+use super::register::{Bitfield, Config, CtrlHum, CtrlMeas, Filter, Mode, Oversampling, Register,
+                      Standby, Writable};
+
+const MAX_OVER_SAMPLING_AND_NORMAL_MODE: CtrlMeas = CtrlMeas {
+    osrs_t: Oversampling::X1,
+    osrs_p: Oversampling::X16,
+    mode: Mode::Normal,
+};
+
+/// Value of the ChipId register on a BME280.
+pub const BME280_CHIP_ID: u8 = 0x60;
+
+/// Writing this value to the SoftReset register resets the sensor.
+const SOFT_RESET_COMMAND: u8 = 0xB6;
+
+/// Raw values the sensor reports for channels whose oversampling is skipped.
+const SKIPPED_TEMPERATURE_OR_PRESSURE: u32 = 0x80000;
+const SKIPPED_HUMIDITY: u32 = 0x8000;
+
+/// The driver's copy of the sensor's configuration registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub osrs_t: Oversampling,
+    pub osrs_p: Oversampling,
+    pub osrs_h: Oversampling,
+    pub filter: Filter,
+    pub standby: Standby,
+}
 
-const BME280OSAMPLE1: u8 = 1;
-#[allow(dead_code)] // Exists in reference source
-const BME280OSAMPLE2: u8 = 2;
-#[allow(dead_code)] // Exists in reference source
-const BME280OSAMPLE4: u8 = 3;
-#[allow(dead_code)] // Exists in reference source
-const BME280OSAMPLE8: u8 = 4;
-#[allow(dead_code)] // Exists in reference source
-const BME280OSAMPLE16: u8 = 5;
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            osrs_t: Oversampling::X1,
+            osrs_p: Oversampling::X1,
+            osrs_h: Oversampling::X1,
+            filter: Filter::Off,
+            standby: Standby::Ms0_5,
+        }
+    }
+}
 
-const MAX_OVER_SAMPLING_AND_NORMAL_MODE: u8 = 0x3F;
+/// Result of comparing the live sensor against the driver's cached configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigurationState {
+    /// The registers hold what the driver last wrote.
+    Intact,
+    /// The registers differ, typically because the sensor was reset.
+    Lost,
+    /// The device does not identify itself as a BME280.
+    WrongChip(u8),
+}
 
 pub struct Bme280<T: I2CDevice<Error = LinuxI2CError> + Sized> {
     calibration: Calibration,
     device: RefCell<T>,
-    mode: u8,
+    settings: Settings,
+    mode: Mode,
+    plausibility: Plausibility,
+    retry_policy: Option<RetryPolicy>,
+    retry_counters: Arc<RetryCounters>,
+    verify_retries: Option<u32>,
+    flatline: RefCell<FlatlineDetector>,
+    bus_lock: Option<BusLock>,
+    bus_lock_held: Cell<bool>,
 }
 
 pub trait Sensor {
     fn read_temperature(&self) -> Result<f64, LinuxI2CError>;
     fn read_pressure(&self) -> Result<f64, LinuxI2CError>;
     fn read_humidity(&self) -> Result<f64, LinuxI2CError>;
+
+    /// Reads all three channels.  Implementations that can take them from
+    /// a single conversion should override this.
+    fn read_measurement(&self) -> Result<Measurement, LinuxI2CError> {
+        let mut measurement = Measurement {
+            temperature: Some(try!(self.read_temperature())),
+            pressure: Some(try!(self.read_pressure())),
+            humidity: Some(try!(self.read_humidity())),
+            precision: None,
+            accuracy: None,
+            implausible: Vec::new(),
+        };
+        measurement.accuracy = Some(accuracy::accuracy(&measurement));
+        Ok(measurement)
+    }
 }
 
 impl<T> Sensor for Bme280<T> 
@@ -47,8 +123,27 @@ impl<T> Sensor for Bme280<T>
         fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
             self.read_humidity()
         }
+        fn read_measurement(&self) -> Result<Measurement, LinuxI2CError> {
+            self.read_measurement()
+        }
+    }
+
+impl Bme280<LinuxI2CDevice> {
+    /// Opens the sensor at `address` on the bus device at `path`, which
+    /// may be any name udev gave it, such as `/dev/i2c-sensors`.
+    pub fn open<P: AsRef<Path>>(path: P, address: Bme280Address) -> Result<Bme280<LinuxI2CDevice>, LinuxI2CError> {
+        let linux_i2c_device = try!(LinuxI2CDevice::new(path, address.value()));
+        Bme280::new_from_device(linux_i2c_device)
     }
 
+    /// Opens the sensor at `address` on the bus whose adapter name, as
+    /// listed in `/sys/bus/i2c/devices/i2c-*/name`, is `bus_name`.
+    pub fn open_bus_name(bus_name: &str, address: Bme280Address) -> Result<Bme280<LinuxI2CDevice>, LinuxI2CError> {
+        let path = try!(bus::find_bus_by_name(bus_name));
+        Bme280::open(path, address)
+    }
+}
+
 impl<T: I2CDevice<Error = LinuxI2CError> + Sized> Bme280<T> {
     // Am torn between keeping these function implementations closely
     // resembling the reference C++ implementation, or instead
@@ -69,87 +164,434 @@ impl<T: I2CDevice<Error = LinuxI2CError> + Sized> Bme280<T> {
     pub fn new_from_device(dev: T) -> Result<Bme280<T>, LinuxI2CError> {
         let mut devmut = dev;
         let cal = try!(Bme280::get_calibration(&mut devmut));
-        try!(devmut.smbus_write_byte_data(Register::Control as u8,
-                                          MAX_OVER_SAMPLING_AND_NORMAL_MODE));
-        Ok(Bme280 {
-               calibration: cal,
-               device: RefCell::new(devmut),
-               mode: BME280OSAMPLE1,
-           })
+        try!(cal.validate());
+        let bme = Bme280 {
+            calibration: cal,
+            device: RefCell::new(devmut),
+            settings: Settings {
+                osrs_t: MAX_OVER_SAMPLING_AND_NORMAL_MODE.osrs_t,
+                osrs_p: MAX_OVER_SAMPLING_AND_NORMAL_MODE.osrs_p,
+                ..Settings::default()
+            },
+            mode: MAX_OVER_SAMPLING_AND_NORMAL_MODE.mode,
+            plausibility: Plausibility::Off,
+            retry_policy: None,
+            retry_counters: Arc::new(RetryCounters::default()),
+            verify_retries: None,
+            flatline: RefCell::new(FlatlineDetector::default()),
+            bus_lock: None,
+            bus_lock_held: Cell::new(false),
+        };
+        // ControlHum and Config are written too, so that the sensor holds
+        // what the cache says rather than its power-on defaults:
+        try!(bme.write_settings(bme.mode));
+        // The data registers hold reset values until the first conversion
+        // in normal mode completes:
+        thread::sleep(bme.measurement_time().max);
+        Ok(bme)
+    }
+
+    /// Switches the sensor's power mode.  In normal mode the sensor converts
+    /// continuously and reads no longer trigger a conversion of their own.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), LinuxI2CError> {
+        {
+            let mut refmut = self.device.borrow_mut();
+            try!(self.write_control(refmut.deref_mut(), mode));
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Returns the power mode the driver last put the sensor in.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Returns the driver's cached copy of the sensor configuration.
+    pub fn settings(&self) -> Settings {
+        self.settings
+    }
+
+    /// Chooses how samples failing plausibility checks are handled.
+    pub fn set_plausibility(&mut self, plausibility: Plausibility) {
+        self.plausibility = plausibility;
+    }
+
+    /// Turns read-back verification of configuration writes on or off.
+    /// With `Some(retries)`, every write to ControlHum, Control or Config is
+    /// read back and rewritten up to `retries` times until it sticks,
+    /// failing with `Bme280Error::WriteMismatch` otherwise.
+    pub fn set_write_verification(&mut self, retries: Option<u32>) {
+        self.verify_retries = retries;
+    }
+
+    /// Returns the expected resolution and noise of readings with the
+    /// current settings.
+    pub fn precision(&self) -> Precision {
+        precision::precision(&self.settings)
+    }
+
+    /// Returns how long one conversion takes with the current settings.
+    pub fn measurement_time(&self) -> MeasurementTime {
+        timing::measurement_time(self.settings.osrs_t, self.settings.osrs_p, self.settings.osrs_h)
+    }
+
+    /// Reads a single register.
+    pub fn read_register(&self, register: Register) -> Result<u8, LinuxI2CError> {
+        let mut refmut = self.device.borrow_mut();
+        refmut.smbus_read_byte_data(register as u8)
+    }
+
+    /// Reads `len` consecutive registers, starting at `start`.
+    pub fn read_registers(&self, start: Register, len: u8) -> Result<Vec<u8>, LinuxI2CError> {
+        let mut refmut = self.device.borrow_mut();
+        let dev = refmut.deref_mut();
+
+        let mut values = Vec::with_capacity(len as usize);
+        for offset in 0..len {
+            let address = (start as u8).wrapping_add(offset);
+            values.push(try!(dev.smbus_read_byte_data(address)));
+        }
+        Ok(values)
+    }
+
+    /// Writes a single register.  Only the control, config and reset
+    /// registers may be written; the driver's cached settings and power
+    /// mode are updated to match.
+    pub fn write_register(&mut self, register: Register, value: u8) -> Result<(), LinuxI2CError> {
+        match register {
+            Register::ControlHum | Register::Control | Register::Config | Register::SoftReset => {}
+            _ => {
+                return Err(Bme280Error::ReadOnlyRegister(register).into())
+            }
+        }
+        {
+            let mut refmut = self.device.borrow_mut();
+            try!(self.write_verified(refmut.deref_mut(), register, value));
+        }
+        self.sync_settings(register, value);
+        Ok(())
+    }
+
+    /// Reads one of the sensor's control or status registers as a typed bitfield.
+    pub fn read_bitfield<B: Bitfield>(&self) -> Result<B, LinuxI2CError> {
+        let mut refmut = self.device.borrow_mut();
+        let raw = try!(refmut.smbus_read_byte_data(B::REGISTER as u8));
+        Ok(B::from(raw))
+    }
+
+    /// Reads a control register, applies `f` to its typed value and writes
+    /// the result back.  Reserved bits are carried over from the value read.
+    /// Returns the value that was written.
+    pub fn modify_bitfield<B, F>(&mut self, f: F) -> Result<B, LinuxI2CError>
+        where B: Writable,
+              F: FnOnce(B) -> B
+    {
+        let mut refmut = self.device.borrow_mut();
+        let dev = refmut.deref_mut();
+
+        let raw = try!(dev.smbus_read_byte_data(B::REGISTER as u8));
+        let value = f(B::from(raw));
+        let bits: u8 = value.into();
+        let new_raw = (raw & !B::MASK) | (bits & B::MASK);
+        try!(self.write_verified(dev, B::REGISTER, new_raw));
+        drop(refmut);
+        self.sync_settings(B::REGISTER, new_raw);
+        Ok(value)
+    }
+
+    /// Keeps the cached settings and mode in line with a value just written
+    /// to `register`.
+    fn sync_settings(&mut self, register: Register, value: u8) {
+        match register {
+            Register::ControlHum => self.settings.osrs_h = CtrlHum::from(value).osrs_h,
+            Register::Control => {
+                let meas = CtrlMeas::from(value);
+                self.settings.osrs_t = meas.osrs_t;
+                self.settings.osrs_p = meas.osrs_p;
+                self.mode = meas.mode;
+            }
+            Register::Config => {
+                let config = Config::from(value);
+                self.settings.filter = config.filter;
+                self.settings.standby = config.t_sb;
+            }
+            Register::SoftReset if value == SOFT_RESET_COMMAND => {
+                // Every control register returns to zero after a reset:
+                self.settings = Settings {
+                    osrs_t: Oversampling::Skipped,
+                    osrs_p: Oversampling::Skipped,
+                    osrs_h: Oversampling::Skipped,
+                    filter: Filter::Off,
+                    standby: Standby::Ms0_5,
+                };
+                self.mode = Mode::Sleep;
+            }
+            _ => {}
+        }
     }
 
     pub fn print_calibration(&self) {
         println!("{}", self.calibration);
     }
 
+    /// Returns a stable hash of the factory calibration, which serves to
+    /// tell individual sensors apart as the BME280 has no serial number.
+    pub fn calibration_fingerprint(&self) -> u64 {
+        self.calibration.fingerprint()
+    }
+
     /// Reads the current Fahrenheit temperature value from the sensor
     pub fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
-        // Technically I'm skipping the step of casting to an integer, which would
-        // result in rounding down of the var1 and var2 that were used in the original
-        // calculation of t_fine:
-        let celcius = try!(self.calc_t_fine()) / 5120.0;
-        let fahrenheit = celcius * 1.8 + 32.0;
-        Ok(fahrenheit)
+        self.run_sequence(|| {
+            let ut = try!(self.read_raw_temp());
+            self.record_sample(Channel::Temperature, ut as u32);
+            let t_fine = self.compensate_t_fine(ut);
+            self.check_range(Channel::Temperature, to_fahrenheit(t_fine))
+        })
     }
 
     /// Reads the current barometric pressure in InHg from the sensor
     pub fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
-        let p1 = self.calibration.p1 as f64;
-        let p2 = self.calibration.p2 as f64;
-        let p3 = self.calibration.p3 as f64;
-        let p4 = self.calibration.p4 as f64;
-        let p5 = self.calibration.p5 as f64;
-        let p6 = self.calibration.p6 as f64;
-        let p7 = self.calibration.p7 as f64;
-        let p8 = self.calibration.p8 as f64;
-        let p9 = self.calibration.p9 as f64;
+        self.run_sequence(|| {
+            let adc = try!(self.read_raw_pressure());
+            self.record_sample(Channel::Pressure, adc);
+            let t_fine = try!(self.calc_t_fine());
+            self.check_range(Channel::Pressure, self.compensate_pressure(adc, t_fine))
+        })
+    }
 
-        let adc = try!(self.read_raw_pressure()) as f64;
-        let t_fine = try!(self.calc_t_fine());
-        let var1 = t_fine / 2.0 - 64000.0;
-        let var2 = var1 * var1 * p6 / 32768.0;
-        let var2_2 = var2 + var1 * p5 * 2.0;
-        let var2_3 = var2_2 / 4.0 + p4 * 65536.0;
-        let var1_2 = (p3 * var1 * var1 / 524288.0 + p2 * var1) / 524288.0;
-        let var1_3 = (1.0 + var1_2 / 32768.0) * p1;
+    pub fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        self.run_sequence(|| {
+            let adc = try!(self.read_raw_humidity());
+            println!("Raw humidity (adc) is: {}", adc);
+            self.record_sample(Channel::Humidity, adc as u32);
+            let t_fine = try!(self.calc_t_fine());
+            self.check_range(Channel::Humidity, self.compensate_humidity(adc, t_fine))
+        })
+    }
 
-        if var1_3 == 0.0 {
-            return Ok(0.0);
+    /// Reads all three channels from a single conversion.  Channels whose
+    /// oversampling is set to skipped come back as `None`; pressure and
+    /// humidity also come back as `None` when temperature is skipped, as
+    /// they cannot be compensated without it.
+    pub fn read_measurement(&self) -> Result<Measurement, LinuxI2CError> {
+        self.run_sequence(|| self.measure(true))
+    }
+
+    /// Retries every subsequent measurement, as a whole, according to
+    /// `policy`.  `None` turns retrying off again.
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.retry_policy = policy;
+    }
+
+    /// Returns the counters of measurement retries and failures.
+    pub fn retry_counters(&self) -> Arc<RetryCounters> {
+        self.retry_counters.clone()
+    }
+
+    /// Reports whether any channel's raw values have flatlined.
+    pub fn health(&self) -> Health {
+        self.flatline.borrow().health()
+    }
+
+    /// Sets how many bit-identical raw samples in a row mark a channel as
+    /// stuck, and forgets the samples seen so far.
+    pub fn set_flatline_threshold(&mut self, samples: usize) {
+        self.flatline = RefCell::new(FlatlineDetector::new(samples));
+    }
+
+    /// Holds `lock` for the duration of every subsequent measurement, so
+    /// that other processes using the same lock file cannot interleave
+    /// their transactions with it.  `None` turns locking off again.
+    pub fn set_bus_lock(&mut self, lock: Option<BusLock>) {
+        self.bus_lock = lock;
+    }
+
+    pub fn bus_lock(&self) -> Option<&BusLock> {
+        self.bus_lock.as_ref()
+    }
+
+    /// Tells the sensor that the caller holds its bus lock, e.g. across a
+    /// whole `SensorGroup` cycle, so measurements must not take it again.
+    pub(crate) fn set_bus_lock_held(&self, held: bool) {
+        self.bus_lock_held.set(held);
+    }
+
+    /// Runs one full measurement sequence, holding the bus lock if there
+    /// is one and retrying according to the retry policy.  The lock is
+    /// released between attempts so that backoff does not hold up others.
+    fn run_sequence<R, F>(&self, mut op: F) -> Result<R, LinuxI2CError>
+        where F: FnMut() -> Result<R, LinuxI2CError>
+    {
+        let mut locked = || {
+            let _guard = match self.bus_lock {
+                Some(ref lock) if !self.bus_lock_held.get() => Some(try!(lock.acquire())),
+                _ => None,
+            };
+            op()
+        };
+        match self.retry_policy {
+            Some(ref policy) => retry::retry(policy, &self.retry_counters, locked),
+            None => locked(),
         }
+    }
 
-        let p = 1048576.0 - adc;
-        let p_2 = ((p - var2_3 / 4096.0) * 6250.0) / var1_3;
-        let var1_4 = p9 * p_2 * p_2 / 2147483648.0;
-        let var2_4 = p_2 * p8 / 32768.0;
-        let pascals = p_2 + (var1_4 + var2_4 + p7) / 16.0;
-        let in_hg = pascals * 0.000295299830714;
-        Ok(in_hg)
+    /// Starts a forced conversion without waiting for it to complete, so
+    /// that several sensors can convert at the same time.  Collect the
+    /// result with `read_conversion` once `measurement_time().max` has
+    /// passed.  Does nothing in normal mode.
+    pub fn start_conversion(&self) -> Result<(), LinuxI2CError> {
+        if self.mode == Mode::Normal {
+            return Ok(());
+        }
+        self.run_sequence(|| {
+            let mut refmut = self.device.borrow_mut();
+            self.write_control(refmut.deref_mut(), Mode::Forced)
+        })
     }
 
-    pub fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
-        let h1 = self.calibration.h1 as f64;
-        let h2 = self.calibration.h2 as f64;
-        let h3 = self.calibration.h3 as f64;
-        let h4 = self.calibration.h4 as f64;
-        let h5 = self.calibration.h5 as f64;
-        let h6 = self.calibration.h6 as f64;
+    /// Reads all three channels of the latest conversion without starting
+    /// another.
+    pub fn read_conversion(&self) -> Result<Measurement, LinuxI2CError> {
+        self.run_sequence(|| self.measure(false))
+    }
 
-        let adc = try!(self.read_raw_humidity());
-        println!("Raw humidity (adc) is: {}", adc);
-        let h = try!(self.calc_t_fine()) - 76800.0;
-        println!("h: {}", h);
-        let h_2 = (adc - (h4 * 64.0 + h5 / 16384.8 * h)) *
-                  (h2 / 65536.0 * (1.0 + h6 / 67108864.0 * h * (1.0 + h3 / 67108864.0 * h)));
-        println!("h_2: {}", h_2);
-        let h_3 = h_2 * (1.0 - h1 * h_2 / 524288.0);
-        println!("h_3: {}", h_3);
-        match h_3 {
-            x if x > 100.0 => Ok(x),
-            x if x < 0.0 => Ok(x),
-            _ => Ok(h_3),
+    fn measure(&self, trigger: bool) -> Result<Measurement, LinuxI2CError> {
+        let (ut, up, uh) = {
+            let mut refmut = self.device.borrow_mut();
+            let dev = refmut.deref_mut();
+
+            if trigger {
+                try!(self.trigger_conversion(dev));
+            }
+            (try!(read_adc_20(dev,
+                              Register::TemperatureData,
+                              Register::TemperatureData1,
+                              Register::TemperatureData2)),
+             try!(read_adc_20(dev,
+                              Register::PressureData,
+                              Register::PressureData1,
+                              Register::PressureData2)),
+             try!(read_adc_16(dev, Register::HumidityData, Register::HumidityData1)))
+        };
+        if ut != SKIPPED_TEMPERATURE_OR_PRESSURE {
+            self.record_sample(Channel::Temperature, ut);
+        }
+        if up != SKIPPED_TEMPERATURE_OR_PRESSURE {
+            self.record_sample(Channel::Pressure, up);
+        }
+        if uh != SKIPPED_HUMIDITY {
+            self.record_sample(Channel::Humidity, uh);
+        }
+
+        let t_fine = match ut {
+            SKIPPED_TEMPERATURE_OR_PRESSURE => None,
+            _ => Some(self.compensate_t_fine(ut as f64)),
+        };
+        let mut measurement = Measurement {
+            temperature: t_fine.map(to_fahrenheit),
+            pressure: match up {
+                SKIPPED_TEMPERATURE_OR_PRESSURE => None,
+                _ => t_fine.map(|t| self.compensate_pressure(up, t)),
+            },
+            humidity: match uh {
+                SKIPPED_HUMIDITY => None,
+                _ => t_fine.map(|t| self.compensate_humidity(uh as f64, t)),
+            },
+            precision: Some(self.precision()),
+            accuracy: None,
+            implausible: Vec::new(),
+        };
+        measurement.accuracy = Some(accuracy::accuracy(&measurement));
+
+        if self.plausibility != Plausibility::Off {
+            let channels = [(Channel::Temperature, ut, measurement.temperature),
+                            (Channel::Pressure, up, measurement.pressure),
+                            (Channel::Humidity, uh, measurement.humidity)];
+            for &(channel, raw, value) in channels.iter() {
+                if let Some(value) = value {
+                    if !plausibility::raw_plausible(channel, raw) ||
+                       !plausibility::in_operating_range(channel, value) {
+                        measurement.implausible.push(channel);
+                    }
+                }
+            }
+            if self.plausibility == Plausibility::Reject {
+                if let Some(&channel) = measurement.implausible.first() {
+                    return Err(Bme280Error::Implausible(channel).into());
+                }
+            }
+        }
+        Ok(measurement)
+    }
+
+    /// Changes oversampling, filter and standby settings, keeping the
+    /// current power mode.  The sensor is put to sleep while Config is
+    /// written, since writes to it may be ignored in normal mode.
+    pub fn configure(&mut self, settings: Settings) -> Result<(), LinuxI2CError> {
+        let previous = self.settings;
+        self.settings = settings;
+        let result = self.write_settings(self.mode);
+        if result.is_err() {
+            self.settings = previous;
+        }
+        result
+    }
+
+    /// Writes the cached settings and power mode to the sensor again, e.g.
+    /// after it has lost them to a reset.
+    pub fn reapply(&self) -> Result<(), LinuxI2CError> {
+        self.write_settings(self.mode)
+    }
+
+    /// Compares the chip ID and the live control and config registers
+    /// against the driver's cached settings and power mode.
+    pub fn check_configuration(&self) -> Result<ConfigurationState, LinuxI2CError> {
+        let mut refmut = self.device.borrow_mut();
+        let dev = refmut.deref_mut();
+
+        let chip_id = try!(dev.smbus_read_byte_data(Register::ChipId as u8));
+        if chip_id != BME280_CHIP_ID {
+            return Ok(ConfigurationState::WrongChip(chip_id));
+        }
+        let hum = CtrlHum::from(try!(dev.smbus_read_byte_data(Register::ControlHum as u8)));
+        let meas = CtrlMeas::from(try!(dev.smbus_read_byte_data(Register::Control as u8)));
+        let config = Config::from(try!(dev.smbus_read_byte_data(Register::Config as u8)));
+
+        // Outside normal mode the sensor drops back to sleep by itself
+        // after every conversion, so only normal mode must match exactly:
+        let mode_matches = (meas.mode == Mode::Normal) == (self.mode == Mode::Normal);
+        if hum.osrs_h == self.settings.osrs_h && meas.osrs_t == self.settings.osrs_t &&
+           meas.osrs_p == self.settings.osrs_p && mode_matches &&
+           config.filter == self.settings.filter && config.t_sb == self.settings.standby {
+            Ok(ConfigurationState::Intact)
+        } else {
+            Ok(ConfigurationState::Lost)
         }
     }
 
+    /// Applies one of the datasheet's recommended presets, including its
+    /// power mode.
+    pub fn apply_preset(&mut self, preset: Preset) -> Result<(), LinuxI2CError> {
+        try!(self.configure(preset.settings()));
+        self.set_mode(preset.mode())
+    }
+
+    fn write_settings(&self, mode: Mode) -> Result<(), LinuxI2CError> {
+        let mut refmut = self.device.borrow_mut();
+        let dev = refmut.deref_mut();
+
+        try!(self.write_control(dev, Mode::Sleep));
+        let config = Config {
+            t_sb: self.settings.standby,
+            filter: self.settings.filter,
+            spi3w_en: false,
+        };
+        try!(self.write_verified(dev, Register::Config, config.into()));
+        self.write_control(dev, mode)
+    }
+
     fn get_calibration(dev: &mut T) -> Result<Calibration, LinuxI2CError> {
         // Why oh why does the reference implementation combine reading calibration
         // values with transforming those values?  Am going to just mimic that
@@ -192,9 +634,11 @@ impl<T: I2CDevice<Error = LinuxI2CError> + Sized> Bme280<T> {
         let mut refmut = self.device.borrow_mut();
         let dev = refmut.deref_mut();
 
-        let msb = try!(dev.smbus_read_byte_data(Register::HumidityData as u8)) as u16;
-        let lsb = try!(dev.smbus_read_byte_data(Register::HumidityData1 as u8)) as u16;
-        let raw = (msb << 8) | lsb;
+        let raw = try!(read_adc_16(dev, Register::HumidityData, Register::HumidityData1));
+        if raw == SKIPPED_HUMIDITY {
+            return Err(Bme280Error::ChannelSkipped(Channel::Humidity).into());
+        }
+        try!(self.check_raw(Channel::Humidity, raw));
         Ok(raw as f64)
     }
 
@@ -202,32 +646,103 @@ impl<T: I2CDevice<Error = LinuxI2CError> + Sized> Bme280<T> {
         let mut refmut = self.device.borrow_mut();
         let dev = refmut.deref_mut();
 
-        try!(dev.smbus_write_byte_data(Register::ControlHum as u8, self.mode));
-        let meas = self.mode << 5 | self.mode << 2 | 1;
-        try!(dev.smbus_write_byte_data(Register::Control as u8, meas));
-        let mut sleep_time = 0.00125 + 0.0023 * (1 << self.mode) as f32;
-        sleep_time = sleep_time + 0.0023 * (1 << self.mode) as f32 + 0.000575;
-        sleep_time = sleep_time + 0.0023 * (1 << self.mode) as f32 + 0.000575;
-        let dur = time::Duration::from_millis((sleep_time * 1000.0) as u64);
-        thread::sleep(dur);
-
-        let msb = try!(dev.smbus_read_byte_data(Register::TemperatureData as u8)) as u32;
-        let lsb = try!(dev.smbus_read_byte_data(Register::TemperatureData1 as u8)) as u32;
-        let xlsb = try!(dev.smbus_read_byte_data(Register::TemperatureData2 as u8)) as u32;
-
-        let raw = ((msb << 16) | (lsb << 8) | xlsb) >> 4;
+        try!(self.trigger_conversion(dev));
+        let raw = try!(read_adc_20(dev,
+                                   Register::TemperatureData,
+                                   Register::TemperatureData1,
+                                   Register::TemperatureData2));
         println!("raw temp: {}", raw as f64);
+        if raw == SKIPPED_TEMPERATURE_OR_PRESSURE {
+            return Err(Bme280Error::ChannelSkipped(Channel::Temperature).into());
+        }
+        try!(self.check_raw(Channel::Temperature, raw));
         Ok(raw as f64)
     }
 
+    /// Feeds the flatline detector one raw value per conversion.  Outside
+    /// normal mode every read follows a conversion of its own.
+    fn record_sample(&self, channel: Channel, raw: u32) {
+        let mut flatline = self.flatline.borrow_mut();
+        if self.mode == Mode::Normal {
+            let period = timing::conversion_period(self.settings.osrs_t,
+                                                   self.settings.osrs_p,
+                                                   self.settings.osrs_h,
+                                                   self.settings.standby);
+            flatline.record_latched(channel, raw, Instant::now(), period);
+        } else {
+            flatline.record(channel, raw);
+        }
+    }
+
+    fn check_raw(&self, channel: Channel, raw: u32) -> Result<(), LinuxI2CError> {
+        if self.plausibility == Plausibility::Reject && !plausibility::raw_plausible(channel, raw) {
+            return Err(Bme280Error::Implausible(channel).into());
+        }
+        Ok(())
+    }
+
+    fn check_range(&self, channel: Channel, value: f64) -> Result<f64, LinuxI2CError> {
+        if self.plausibility == Plausibility::Reject &&
+           !plausibility::in_operating_range(channel, value) {
+            return Err(Bme280Error::Implausible(channel).into());
+        }
+        Ok(value)
+    }
+
+    /// Starts a conversion and waits for it to complete, unless the sensor
+    /// is already converting continuously in normal mode.
+    fn trigger_conversion(&self, dev: &mut T) -> Result<(), LinuxI2CError> {
+        if self.mode != Mode::Normal {
+            try!(self.write_control(dev, Mode::Forced));
+            thread::sleep(self.measurement_time().max);
+        }
+        Ok(())
+    }
+
+    fn write_control(&self, dev: &mut T, mode: Mode) -> Result<(), LinuxI2CError> {
+        // The sensor only latches ControlHum on the next write to Control,
+        // so the two must always be written together and in this order:
+        let hum = CtrlHum { osrs_h: self.settings.osrs_h };
+        try!(self.write_verified(dev, Register::ControlHum, hum.into()));
+        let meas = CtrlMeas {
+            osrs_t: self.settings.osrs_t,
+            osrs_p: self.settings.osrs_p,
+            mode,
+        };
+        self.write_verified(dev, Register::Control, meas.into())
+    }
+
+    /// Writes a register and, when write verification is on, reads it back
+    /// and rewrites it until it holds `value` or the retries run out.
+    fn write_verified(&self, dev: &mut T, register: Register, value: u8) -> Result<(), LinuxI2CError> {
+        try!(dev.smbus_write_byte_data(register as u8, value));
+        let retries = match self.verify_retries {
+            Some(retries) => retries,
+            None => return Ok(()),
+        };
+        let mask = verify_mask(register, value);
+        let mut attempt = 0;
+        loop {
+            let read = try!(dev.smbus_read_byte_data(register as u8));
+            if read & mask == value & mask {
+                return Ok(());
+            }
+            if attempt >= retries {
+                return Err(Bme280Error::WriteMismatch {
+                               register,
+                               wrote: value,
+                               read,
+                           }
+                           .into());
+            }
+            attempt += 1;
+            try!(dev.smbus_write_byte_data(register as u8, value));
+        }
+    }
+
     fn calc_t_fine(&self) -> Result<f64, LinuxI2CError> {
         let ut = try!(self.read_raw_temp());
-        let t1 = self.calibration.t1 as f64;
-        let t2 = self.calibration.t2 as f64;
-        let t3 = self.calibration.t3 as f64;
-        let var1 = (ut / 16384.0 - t1 / 1024.0) * t2;
-        let var2 = ((ut / 131072.0 - t1 / 8192.0) * (ut / 131072.0 - t1 / 8192.0)) * t3;
-        let t_fine = var1 + var2;
+        let t_fine = self.compensate_t_fine(ut);
         println!("t_fine: {}", t_fine);
         Ok(t_fine)
     }
@@ -236,11 +751,124 @@ impl<T: I2CDevice<Error = LinuxI2CError> + Sized> Bme280<T> {
         let mut refmut = self.device.borrow_mut();
         let dev = refmut.deref_mut();
 
-        let msb = try!(dev.smbus_read_byte_data(Register::PressureData as u8)) as u32;
-        let lsb = try!(dev.smbus_read_byte_data(Register::PressureData1 as u8)) as u32;
-        let xlsb = try!(dev.smbus_read_byte_data(Register::PressureData2 as u8)) as u32;
-        let raw = ((msb << 16) | (lsb << 8) | xlsb) >> 4;
+        let raw = try!(read_adc_20(dev,
+                                   Register::PressureData,
+                                   Register::PressureData1,
+                                   Register::PressureData2));
         println!("raw pressure: {}", raw);
+        if raw == SKIPPED_TEMPERATURE_OR_PRESSURE {
+            return Err(Bme280Error::ChannelSkipped(Channel::Pressure).into());
+        }
+        try!(self.check_raw(Channel::Pressure, raw));
         Ok(raw)
     }
+
+    fn compensate_t_fine(&self, ut: f64) -> f64 {
+        let t1 = self.calibration.t1 as f64;
+        let t2 = self.calibration.t2 as f64;
+        let t3 = self.calibration.t3 as f64;
+        let var1 = (ut / 16384.0 - t1 / 1024.0) * t2;
+        let var2 = ((ut / 131072.0 - t1 / 8192.0) * (ut / 131072.0 - t1 / 8192.0)) * t3;
+        var1 + var2
+    }
+
+    /// Converts a raw pressure reading to InHg
+    fn compensate_pressure(&self, adc: u32, t_fine: f64) -> f64 {
+        let p1 = self.calibration.p1 as f64;
+        let p2 = self.calibration.p2 as f64;
+        let p3 = self.calibration.p3 as f64;
+        let p4 = self.calibration.p4 as f64;
+        let p5 = self.calibration.p5 as f64;
+        let p6 = self.calibration.p6 as f64;
+        let p7 = self.calibration.p7 as f64;
+        let p8 = self.calibration.p8 as f64;
+        let p9 = self.calibration.p9 as f64;
+
+        let adc = adc as f64;
+        let var1 = t_fine / 2.0 - 64000.0;
+        let var2 = var1 * var1 * p6 / 32768.0;
+        let var2_2 = var2 + var1 * p5 * 2.0;
+        let var2_3 = var2_2 / 4.0 + p4 * 65536.0;
+        let var1_2 = (p3 * var1 * var1 / 524288.0 + p2 * var1) / 524288.0;
+        let var1_3 = (1.0 + var1_2 / 32768.0) * p1;
+
+        if var1_3 == 0.0 {
+            return 0.0;
+        }
+
+        let p = 1048576.0 - adc;
+        let p_2 = ((p - var2_3 / 4096.0) * 6250.0) / var1_3;
+        let var1_4 = p9 * p_2 * p_2 / 2147483648.0;
+        let var2_4 = p_2 * p8 / 32768.0;
+        let pascals = p_2 + (var1_4 + var2_4 + p7) / 16.0;
+        pascals * IN_HG_PER_PASCAL
+    }
+
+    /// Converts a raw humidity reading to percent relative humidity
+    fn compensate_humidity(&self, adc: f64, t_fine: f64) -> f64 {
+        let h1 = self.calibration.h1 as f64;
+        let h2 = self.calibration.h2 as f64;
+        let h3 = self.calibration.h3 as f64;
+        let h4 = self.calibration.h4 as f64;
+        let h5 = self.calibration.h5 as f64;
+        let h6 = self.calibration.h6 as f64;
+
+        let h = t_fine - 76800.0;
+        println!("h: {}", h);
+        let h_2 = (adc - (h4 * 64.0 + h5 / 16384.8 * h)) *
+                  (h2 / 65536.0 * (1.0 + h6 / 67108864.0 * h * (1.0 + h3 / 67108864.0 * h)));
+        println!("h_2: {}", h_2);
+        let h_3 = h_2 * (1.0 - h1 * h_2 / 524288.0);
+        println!("h_3: {}", h_3);
+        h_3
+    }
 }
+
+/// Bits of `register` that must read back as written.  Reserved bits are
+/// left out, as are the mode bits after starting a forced conversion,
+/// since the sensor clears them by itself once the conversion completes.
+fn verify_mask(register: Register, value: u8) -> u8 {
+    match register {
+        Register::ControlHum => CtrlHum::MASK,
+        Register::Control => {
+            match Mode::from(value) {
+                Mode::Forced => CtrlMeas::MASK & !0b11,
+                _ => CtrlMeas::MASK,
+            }
+        }
+        Register::Config => Config::MASK,
+        // The reset register always reads back as zero:
+        _ => 0,
+    }
+}
+
+fn to_fahrenheit(t_fine: f64) -> f64 {
+    // Technically I'm skipping the step of casting to an integer, which would
+    // result in rounding down of the var1 and var2 that were used in the original
+    // calculation of t_fine:
+    let celcius = t_fine / 5120.0;
+    celcius * FAHRENHEIT_PER_CELSIUS + 32.0
+}
+
+/// Reads a 20-bit pressure or temperature ADC value spread over three registers.
+fn read_adc_20<T>(dev: &mut T,
+                  msb: Register,
+                  lsb: Register,
+                  xlsb: Register)
+                  -> Result<u32, LinuxI2CError>
+    where T: I2CDevice<Error = LinuxI2CError> + Sized
+{
+    let msb = try!(dev.smbus_read_byte_data(msb as u8)) as u32;
+    let lsb = try!(dev.smbus_read_byte_data(lsb as u8)) as u32;
+    let xlsb = try!(dev.smbus_read_byte_data(xlsb as u8)) as u32;
+    Ok(((msb << 16) | (lsb << 8) | xlsb) >> 4)
+}
+
+/// Reads a 16-bit humidity ADC value spread over two registers.
+fn read_adc_16<T>(dev: &mut T, msb: Register, lsb: Register) -> Result<u32, LinuxI2CError>
+    where T: I2CDevice<Error = LinuxI2CError> + Sized
+{
+    let msb = try!(dev.smbus_read_byte_data(msb as u8)) as u32;
+    let lsb = try!(dev.smbus_read_byte_data(lsb as u8)) as u32;
+    Ok((msb << 8) | lsb)
+}
\ No newline at end of file
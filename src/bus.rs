@@ -0,0 +1,54 @@
+//! Locating Linux I2C bus devices.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use i2cdev::linux::LinuxI2CError;
+
+use super::error::Bme280Error;
+
+/// Where the kernel lists I2C adapters and the devices on them.
+pub const SYSFS_I2C_DEVICES: &str = "/sys/bus/i2c/devices";
+
+/// Returns the `/dev/i2c-N` path of the bus whose adapter name, as listed
+/// in `/sys/bus/i2c/devices/i2c-N/name`, is `name`.
+pub fn find_bus_by_name(name: &str) -> Result<PathBuf, LinuxI2CError> {
+    find_bus_by_name_in(Path::new(SYSFS_I2C_DEVICES), name)
+}
+
+/// Like `find_bus_by_name`, but reads the adapter list from `sysfs_devices`
+/// instead of the real sysfs.
+pub fn find_bus_by_name_in(sysfs_devices: &Path, name: &str) -> Result<PathBuf, LinuxI2CError> {
+    for number in try!(bus_numbers_in(sysfs_devices)) {
+        let path = sysfs_devices.join(format!("i2c-{}", number)).join("name");
+        // Adapters without a readable name cannot be the one asked for:
+        if let Ok(adapter) = fs::read_to_string(path) {
+            if adapter.trim() == name {
+                return Ok(device_path(number));
+            }
+        }
+    }
+    Err(Bme280Error::UnknownBus(name.to_string()).into())
+}
+
+/// Returns the numbers of the I2C buses listed in `sysfs_devices`, in
+/// ascending order.
+pub fn bus_numbers_in(sysfs_devices: &Path) -> io::Result<Vec<u32>> {
+    let mut numbers = Vec::new();
+    for entry in try!(fs::read_dir(sysfs_devices)) {
+        let file_name = try!(entry).file_name();
+        let file_name = file_name.to_string_lossy();
+        if let Some(number) = file_name.strip_prefix("i2c-") {
+            if let Ok(number) = number.parse() {
+                numbers.push(number);
+            }
+        }
+    }
+    numbers.sort();
+    Ok(numbers)
+}
+
+/// Returns the character device of bus `number`.
+pub fn device_path(number: u32) -> PathBuf {
+    PathBuf::from(format!("/dev/i2c-{}", number))
+}
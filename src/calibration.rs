@@ -1,3 +1,4 @@
+use super::error::Bme280Error;
 use std::fmt::Display;
 use std::fmt::Result;
 use std::fmt::Formatter;
@@ -37,3 +38,71 @@ impl Display for Calibration {
         // fmt::Debug::fmt(self, f)
     }
 }
+
+impl Calibration {
+    /// Rejects coefficients that cannot have come from a working sensor,
+    /// such as the all-zero or all-0xFF blocks a disconnected device returns.
+    pub fn validate(&self) -> ::std::result::Result<(), Bme280Error> {
+        if self.t1 == 0 || self.t1 == 0xFFFF {
+            return Err(Bme280Error::InvalidCalibration("t1 is blank"));
+        }
+        if self.p1 == 0 || self.p1 == 0xFFFF {
+            return Err(Bme280Error::InvalidCalibration("p1 is blank"));
+        }
+        // t2 is the temperature's linear coefficient, which is positive on
+        // every part; a negative or zero value means the block is garbage.
+        if self.t2 <= 0 {
+            return Err(Bme280Error::InvalidCalibration("t2 is not positive"));
+        }
+        // The datasheet gives only the types of the remaining coefficients,
+        // so these bounds are generous margins around values seen on real
+        // parts.  They catch a block that is partly erased or misread.
+        let checks: [(&'static str, i32, i32, i32); 14] = [
+            ("t3 is out of range", self.t3 as i32, -4000, 4000),
+            ("p2 is out of range", self.p2 as i32, -20000, -1000),
+            ("p3 is out of range", self.p3 as i32, 1000, 8000),
+            ("p4 is out of range", self.p4 as i32, 500, 12000),
+            ("p5 is out of range", self.p5 as i32, -1000, 1000),
+            ("p6 is out of range", self.p6 as i32, -100, 100),
+            ("p7 is out of range", self.p7 as i32, 1000, 25000),
+            ("p8 is out of range", self.p8 as i32, -25000, -1000),
+            ("p9 is out of range", self.p9 as i32, 1000, 12000),
+            ("h1 is out of range", self.h1 as i32, 1, 254),
+            ("h2 is out of range", self.h2 as i32, 100, 1000),
+            ("h4 is out of range", self.h4, 100, 1000),
+            ("h5 is out of range", self.h5, -500, 500),
+            ("h6 is out of range", self.h6 as i32, 0, 127),
+        ];
+        for &(message, value, min, max) in checks.iter() {
+            if value < min || value > max {
+                return Err(Bme280Error::InvalidCalibration(message));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a hash of the coefficients that is stable across runs and
+    /// Rust versions.  Calibration differs from part to part, so this
+    /// tells individual sensors apart.
+    pub fn fingerprint(&self) -> u64 {
+        // 64-bit FNV-1a over the coefficients in register order:
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.t1.to_le_bytes());
+        bytes.extend_from_slice(&self.t2.to_le_bytes());
+        bytes.extend_from_slice(&self.t3.to_le_bytes());
+        bytes.extend_from_slice(&self.p1.to_le_bytes());
+        for p in &[self.p2, self.p3, self.p4, self.p5, self.p6, self.p7, self.p8, self.p9] {
+            bytes.extend_from_slice(&p.to_le_bytes());
+        }
+        bytes.push(self.h1);
+        bytes.extend_from_slice(&self.h2.to_le_bytes());
+        bytes.push(self.h3);
+        bytes.extend_from_slice(&self.h4.to_le_bytes());
+        bytes.extend_from_slice(&self.h5.to_le_bytes());
+        bytes.extend_from_slice(&self.h6.to_le_bytes());
+
+        bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+}
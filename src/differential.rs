@@ -0,0 +1,130 @@
+//! Pressure difference between two sensors, e.g. either side of an HVAC
+//! filter to estimate how clogged it is.
+
+use std::collections::VecDeque;
+use std::time::SystemTime;
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CError;
+
+use super::bme280::Bme280;
+use super::error::Bme280Error;
+use super::group::SensorGroup;
+use super::measurement::{Channel, Measurement, IN_HG_PER_PASCAL};
+
+/// One synchronized sample of both sensors.  Pressures are in Pascals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifferentialSample {
+    pub at: SystemTime,
+    pub upstream: f64,
+    pub downstream: f64,
+    /// Upstream minus downstream, less the offset found when zeroing.
+    pub difference: f64,
+    /// `difference` averaged over the filter window.
+    pub filtered: f64,
+}
+
+/// Two sensors whose conversions run at the same time, so that changes in
+/// ambient pressure cancel out of the difference.
+pub struct DifferentialPressure<T: I2CDevice<Error = LinuxI2CError> + Sized> {
+    group: SensorGroup<T>,
+    offset: f64,
+    window: usize,
+    history: VecDeque<f64>,
+}
+
+impl<T: I2CDevice<Error = LinuxI2CError> + Sized> DifferentialPressure<T> {
+    /// Pairs the two sensors, switching any in normal mode to forced mode
+    /// so that every sample triggers both conversions together.
+    pub fn new(upstream: Bme280<T>, downstream: Bme280<T>) -> Result<DifferentialPressure<T>, LinuxI2CError> {
+        let mut group = SensorGroup::new();
+        try!(group.push(upstream));
+        try!(group.push(downstream));
+        Ok(DifferentialPressure {
+               group,
+               offset: 0.0,
+               window: 1,
+               history: VecDeque::new(),
+           })
+    }
+
+    /// Averages the reported difference over the last `samples` samples.
+    /// One, the default, turns filtering off.
+    pub fn set_filter_window(&mut self, samples: usize) {
+        self.window = samples.max(1);
+        self.history.clear();
+    }
+
+    /// Static offset between the sensors, in Pascals, that is subtracted
+    /// from every difference.
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Takes `samples` samples while both sensors see the same pressure and
+    /// stores their mean difference as the offset to cancel.  Returns the
+    /// new offset.
+    pub fn zero(&mut self, samples: usize) -> Result<f64, LinuxI2CError> {
+        let samples = samples.max(1);
+        let mut total = 0.0;
+        for _ in 0..samples {
+            let (_, upstream, downstream) = try!(self.read_pair());
+            total += upstream - downstream;
+        }
+        self.offset = total / samples as f64;
+        self.history.clear();
+        Ok(self.offset)
+    }
+
+    /// Forgets the offset found by `zero`.
+    pub fn clear_zero(&mut self) {
+        self.offset = 0.0;
+        self.history.clear();
+    }
+
+    /// Samples both sensors.
+    pub fn sample(&mut self) -> Result<DifferentialSample, LinuxI2CError> {
+        let (at, upstream, downstream) = try!(self.read_pair());
+        let difference = upstream - downstream - self.offset;
+        if self.history.len() == self.window {
+            self.history.pop_front();
+        }
+        self.history.push_back(difference);
+        let filtered = self.history.iter().sum::<f64>() / self.history.len() as f64;
+        Ok(DifferentialSample {
+               at,
+               upstream,
+               downstream,
+               difference,
+               filtered,
+           })
+    }
+
+    /// Returns the upstream and downstream sensors.
+    pub fn into_inner(self) -> (Bme280<T>, Bme280<T>) {
+        let mut sensors = self.group.into_inner().into_iter();
+        match (sensors.next(), sensors.next()) {
+            (Some(upstream), Some(downstream)) => (upstream, downstream),
+            _ => unreachable!("the group always holds both sensors"),
+        }
+    }
+
+    /// Reads both sensors from simultaneous conversions, in Pascals.
+    fn read_pair(&self) -> Result<(SystemTime, f64, f64), LinuxI2CError> {
+        let measurement = self.group.read_measurements();
+        let mut results = measurement.results.into_iter();
+        let upstream = try!(pascals(results.next()));
+        let downstream = try!(pascals(results.next()));
+        Ok((measurement.at, upstream, downstream))
+    }
+}
+
+fn pascals(result: Option<Result<Measurement, LinuxI2CError>>) -> Result<f64, LinuxI2CError> {
+    let measurement = match result {
+        Some(result) => try!(result),
+        None => unreachable!("the group always holds both sensors"),
+    };
+    match measurement.pressure {
+        Some(in_hg) => Ok(in_hg / IN_HG_PER_PASCAL),
+        None => Err(Bme280Error::ChannelSkipped(Channel::Pressure).into()),
+    }
+}
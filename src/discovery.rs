@@ -0,0 +1,106 @@
+//! Finding BME280-family sensors on the system's I2C buses.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+
+use super::address::Bme280Address;
+use super::bus;
+use super::register::Register;
+
+/// Members of the family that answer on the BME280's addresses and share
+/// its chip ID register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    /// Pressure and temperature only.
+    Bmp280,
+    Bme280,
+    /// Adds a gas sensor; its register map differs from the BME280's.
+    Bme680,
+}
+
+impl Variant {
+    /// Identifies the variant from the value of its ChipId register.
+    pub fn from_chip_id(id: u8) -> Option<Variant> {
+        match id {
+            // 0x56 and 0x57 are engineering samples of the BMP280:
+            0x56..=0x58 => Some(Variant::Bmp280),
+            0x60 => Some(Variant::Bme280),
+            0x61 => Some(Variant::Bme680),
+            _ => None,
+        }
+    }
+}
+
+/// A sensor found on a bus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovered {
+    pub bus: PathBuf,
+    pub address: Bme280Address,
+    pub variant: Variant,
+}
+
+/// Probes both addresses on every `/dev/i2c-N` bus, in ascending order
+/// of N.
+pub fn discover() -> io::Result<Vec<Discovered>> {
+    let mut numbers = Vec::new();
+    for entry in try!(fs::read_dir("/dev")) {
+        let path = try!(entry).path();
+        // Only the kernel's own names, as udev aliases would list buses twice:
+        let number = path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("i2c-"))
+            .and_then(|number| number.parse::<u32>().ok());
+        if let Some(number) = number {
+            numbers.push(number);
+        }
+    }
+    // By number, as sorting the paths would put i2c-10 before i2c-2:
+    numbers.sort();
+    let buses: Vec<PathBuf> = numbers.into_iter().map(bus::device_path).collect();
+    Ok(discover_on(&buses))
+}
+
+/// Probes both addresses on each of `buses`, given as device paths.
+pub fn discover_on<P: AsRef<Path>>(buses: &[P]) -> Vec<Discovered> {
+    discover_with(buses, |bus, address| LinuxI2CDevice::new(bus, address))
+}
+
+/// Probes both addresses on each of `buses`, opening devices with `open`.
+/// Addresses that cannot be opened or read, or whose chip ID is not that of
+/// a family member, are left out.
+pub fn discover_with<P, T, F>(buses: &[P], mut open: F) -> Vec<Discovered>
+    where P: AsRef<Path>,
+          T: I2CDevice<Error = LinuxI2CError>,
+          F: FnMut(&Path, u16) -> Result<T, LinuxI2CError>
+{
+    let mut found = Vec::new();
+    for bus in buses {
+        for &address in Bme280Address::ALL.iter() {
+            let variant = match open(bus.as_ref(), address.value()) {
+                Ok(mut dev) => probe(&mut dev),
+                Err(_) => None,
+            };
+            if let Some(variant) = variant {
+                found.push(Discovered {
+                               bus: bus.as_ref().to_path_buf(),
+                               address,
+                               variant,
+                           });
+            }
+        }
+    }
+    found
+}
+
+/// Reads the chip ID of the device behind `dev`, returning its variant if
+/// it responds as a family member.
+pub fn probe<T>(dev: &mut T) -> Option<Variant>
+    where T: I2CDevice<Error = LinuxI2CError>
+{
+    dev.smbus_read_byte_data(Register::ChipId as u8)
+        .ok()
+        .and_then(Variant::from_chip_id)
+}
@@ -0,0 +1,119 @@
+//! Switching a sensor between the kernel's IIO driver and user-space
+//! access, through the I2C bus's sysfs files.
+
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use i2cdev::linux::LinuxI2CError;
+
+use super::address::Bme280Address;
+
+/// Kernel name of the chip, as given to `new_device`.
+const DEVICE_TYPE: &str = "bme280";
+
+/// Where the sensor at a bus/address stands with the kernel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    /// The kernel has no device at the address; i2c-dev can use it.
+    Absent,
+    /// The kernel has a device but no driver bound to it; i2c-dev can use it.
+    Unbound,
+    /// The named driver owns the device, so i2c-dev gets EBUSY.
+    Bound(String),
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Binding::Absent => write!(f, "no kernel device"),
+            Binding::Unbound => write!(f, "kernel device without driver"),
+            Binding::Bound(ref driver) => write!(f, "bound to {}", driver),
+        }
+    }
+}
+
+/// Inspects and changes kernel bindings under a sysfs root, normally `/sys`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverControl {
+    root: PathBuf,
+}
+
+impl Default for DriverControl {
+    fn default() -> DriverControl {
+        DriverControl::new("/sys")
+    }
+}
+
+impl DriverControl {
+    /// Works on the sysfs tree mounted at `root`.
+    pub fn new<P: AsRef<Path>>(root: P) -> DriverControl {
+        DriverControl { root: root.as_ref().to_path_buf() }
+    }
+
+    /// Reports whether the kernel has a device, and a driver bound to it,
+    /// at `address` on bus `bus`.
+    pub fn binding(&self, bus: u32, address: Bme280Address) -> Result<Binding, LinuxI2CError> {
+        let device = self.device_dir(bus, address);
+        if !device.exists() {
+            return Ok(Binding::Absent);
+        }
+        match fs::read_link(device.join("driver")) {
+            Ok(target) => {
+                let driver = target.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                Ok(Binding::Bound(driver))
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Binding::Unbound),
+            Err(err) => Err(LinuxI2CError::Io(err)),
+        }
+    }
+
+    /// Asks the kernel to create a device, which its driver then claims.
+    pub fn instantiate(&self, bus: u32, address: Bme280Address) -> Result<(), LinuxI2CError> {
+        let line = format!("{} {:#04x}", DEVICE_TYPE, address.value());
+        self.write(self.adapter_dir(bus).join("new_device"), &line)
+    }
+
+    /// Unbinds the kernel driver from the device, leaving the device in
+    /// place.  Does nothing if no driver is bound.
+    pub fn release(&self, bus: u32, address: Bme280Address) -> Result<(), LinuxI2CError> {
+        if let Binding::Bound(_) = try!(self.binding(bus, address)) {
+            let device = self.device_dir(bus, address);
+            try!(self.write(device.join("driver").join("unbind"), &device_name(bus, address)));
+        }
+        Ok(())
+    }
+
+    /// Binds `driver` to an unbound device.
+    pub fn bind(&self, bus: u32, address: Bme280Address, driver: &str) -> Result<(), LinuxI2CError> {
+        let bind = self.root.join("bus/i2c/drivers").join(driver).join("bind");
+        self.write(bind, &device_name(bus, address))
+    }
+
+    /// Removes a device created with `instantiate`, unbinding its driver.
+    /// Devices declared by the device tree cannot be deleted; use `release`.
+    pub fn delete(&self, bus: u32, address: Bme280Address) -> Result<(), LinuxI2CError> {
+        let line = format!("{:#04x}", address.value());
+        self.write(self.adapter_dir(bus).join("delete_device"), &line)
+    }
+
+    fn adapter_dir(&self, bus: u32) -> PathBuf {
+        self.root.join("bus/i2c/devices").join(format!("i2c-{}", bus))
+    }
+
+    fn device_dir(&self, bus: u32, address: Bme280Address) -> PathBuf {
+        self.root.join("bus/i2c/devices").join(device_name(bus, address))
+    }
+
+    fn write(&self, path: PathBuf, line: &str) -> Result<(), LinuxI2CError> {
+        try!(fs::write(path, line));
+        Ok(())
+    }
+}
+
+/// The kernel's name for the device, e.g. `1-0076`.
+fn device_name(bus: u32, address: Bme280Address) -> String {
+    format!("{}-{:04x}", bus, address.value())
+}
@@ -0,0 +1,90 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt;
+use std::io;
+use i2cdev::linux::LinuxI2CError;
+
+use super::measurement::Channel;
+use super::register::Register;
+
+/// Problems detected by the driver itself rather than reported by the bus.
+/// So that every method can keep returning `LinuxI2CError`, these travel
+/// inside `LinuxI2CError::Io`; use `Bme280Error::find` to get them back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bme280Error {
+    /// The channel's oversampling is set to skipped, so there is no value.
+    ChannelSkipped(Channel),
+    /// The register cannot be written.
+    ReadOnlyRegister(Register),
+    /// The channel's raw or compensated value failed a plausibility check.
+    Implausible(Channel),
+    /// The calibration block read from the sensor is unusable.
+    InvalidCalibration(&'static str),
+    /// The device's chip ID is not that of a BME280.
+    WrongChip(u8),
+    /// A configuration register did not hold the written value on read-back.
+    WriteMismatch { register: Register, wrote: u8, read: u8 },
+    /// No I2C bus has the given adapter name.
+    UnknownBus(String),
+    /// The multiplexer has no downstream channel with this number.
+    InvalidMuxChannel(u8),
+    /// No BME280 is bound to the kernel's IIO driver.
+    NoIioDevice,
+    /// The backend cannot do what was asked.
+    Unsupported(&'static str),
+}
+
+impl Bme280Error {
+    /// Returns the driver error carried by `err`, if there is one.
+    pub fn find(err: &LinuxI2CError) -> Option<&Bme280Error> {
+        match *err {
+            LinuxI2CError::Io(ref io_err) => {
+                io_err.get_ref().and_then(|inner| inner.downcast_ref::<Bme280Error>())
+            }
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> io::ErrorKind {
+        match *self {
+            Bme280Error::ChannelSkipped(_) |
+            Bme280Error::UnknownBus(_) |
+            Bme280Error::NoIioDevice => io::ErrorKind::NotFound,
+            Bme280Error::ReadOnlyRegister(_) |
+            Bme280Error::InvalidMuxChannel(_) |
+            Bme280Error::Unsupported(_) => io::ErrorKind::InvalidInput,
+            Bme280Error::Implausible(_) |
+            Bme280Error::InvalidCalibration(_) |
+            Bme280Error::WrongChip(_) |
+            Bme280Error::WriteMismatch { .. } => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl Display for Bme280Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Bme280Error::ChannelSkipped(channel) => write!(f, "{} channel is skipped", channel),
+            Bme280Error::ReadOnlyRegister(register) => write!(f, "register {} is read-only", register),
+            Bme280Error::Implausible(channel) => write!(f, "implausible {} reading", channel),
+            Bme280Error::InvalidCalibration(reason) => write!(f, "invalid calibration: {}", reason),
+            Bme280Error::WrongChip(id) => write!(f, "unexpected chip ID {:#04x}", id),
+            Bme280Error::WriteMismatch { register, wrote, read } => {
+                write!(f, "wrote {:#04x} to {} but read back {:#04x}", wrote, register, read)
+            }
+            Bme280Error::UnknownBus(ref name) => write!(f, "no I2C bus named \"{}\"", name),
+            Bme280Error::InvalidMuxChannel(channel) => write!(f, "no multiplexer channel {}", channel),
+            Bme280Error::NoIioDevice => write!(f, "no BME280 bound to the IIO driver"),
+            Bme280Error::Unsupported(what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl Error for Bme280Error {}
+
+impl From<Bme280Error> for LinuxI2CError {
+    fn from(err: Bme280Error) -> LinuxI2CError {
+        LinuxI2CError::Io(io::Error::new(err.kind(), err))
+    }
+}
@@ -0,0 +1,206 @@
+//! Combining redundant sensors into one voted reading, and spotting the
+//! sensor that has drifted away from the others.
+
+use std::io;
+use i2cdev::linux::LinuxI2CError;
+
+use super::accuracy;
+use super::bme280::Sensor;
+use super::measurement::{Channel, Measurement, FAHRENHEIT_PER_CELSIUS, IN_HG_PER_PASCAL};
+
+/// How the members' values are combined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FusionMethod {
+    Mean,
+    Median,
+    /// Mean after dropping the given fraction, e.g. 0.25, of the values
+    /// from each end.  At least one value is always kept.
+    TrimmedMean(f64),
+}
+
+/// One channel's voted value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fused {
+    pub value: f64,
+    /// Difference between the highest and lowest member value.
+    pub spread: f64,
+    /// Indices of the members further from `value` than the threshold.
+    pub disagreeing: Vec<usize>,
+    /// Number of members that contributed a value.
+    pub contributors: usize,
+}
+
+/// Voted values of every channel, with the members that failed to read.
+#[derive(Debug)]
+pub struct FusedMeasurement {
+    pub temperature: Option<Fused>,
+    pub pressure: Option<Fused>,
+    pub humidity: Option<Fused>,
+    pub errors: Vec<(usize, LinuxI2CError)>,
+}
+
+/// Several sensors voting on each reading.  Implements `Sensor` itself, so
+/// the voted values can be used wherever a single sensor is expected.
+pub struct SensorFusion {
+    sensors: Vec<Box<dyn Sensor>>,
+    method: FusionMethod,
+    thresholds: [f64; Channel::COUNT],
+}
+
+impl SensorFusion {
+    /// Creates an empty fusion.  The disagreement thresholds default to
+    /// twice each channel's absolute accuracy, the furthest two sensors
+    /// that are both within specification can be apart: 2 C, 2 hPa and
+    /// 6 %RH, in reading units.
+    pub fn new(method: FusionMethod) -> SensorFusion {
+        SensorFusion {
+            sensors: Vec::new(),
+            method,
+            thresholds: [2.0 * FAHRENHEIT_PER_CELSIUS, 200.0 * IN_HG_PER_PASCAL, 6.0],
+        }
+    }
+
+    /// Adds a sensor and returns its index in `Fused::disagreeing`.
+    pub fn push<S: Sensor + 'static>(&mut self, sensor: S) -> usize {
+        self.sensors.push(Box::new(sensor));
+        self.sensors.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    /// Sets how far, in reading units, a member may be from the voted
+    /// value before it counts as disagreeing.
+    pub fn set_threshold(&mut self, channel: Channel, threshold: f64) {
+        self.thresholds[channel.index()] = threshold;
+    }
+
+    /// Reads every member once and votes on each channel.
+    pub fn read_fused(&self) -> FusedMeasurement {
+        let mut values: [Vec<(usize, f64)>; Channel::COUNT] = [Vec::new(), Vec::new(), Vec::new()];
+        let mut errors = Vec::new();
+        for (i, sensor) in self.sensors.iter().enumerate() {
+            match sensor.read_measurement() {
+                Ok(measurement) => {
+                    let channels = [measurement.temperature, measurement.pressure, measurement.humidity];
+                    for (channel_values, value) in values.iter_mut().zip(channels.iter()) {
+                        if let Some(value) = *value {
+                            channel_values.push((i, value));
+                        }
+                    }
+                }
+                Err(err) => errors.push((i, err)),
+            }
+        }
+        FusedMeasurement {
+            temperature: self.fuse(Channel::Temperature, &values[Channel::Temperature.index()]),
+            pressure: self.fuse(Channel::Pressure, &values[Channel::Pressure.index()]),
+            humidity: self.fuse(Channel::Humidity, &values[Channel::Humidity.index()]),
+            errors,
+        }
+    }
+
+    /// Votes on one channel, given (member index, value) pairs.
+    fn fuse(&self, channel: Channel, values: &[(usize, f64)]) -> Option<Fused> {
+        let plain: Vec<f64> = values.iter().map(|&(_, value)| value).collect();
+        combine(self.method, &plain).map(|value| {
+            let min = plain.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = plain.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let threshold = self.thresholds[channel.index()];
+            Fused {
+                value,
+                spread: max - min,
+                disagreeing: values.iter()
+                    .filter(|&&(_, member)| (member - value).abs() > threshold)
+                    .map(|&(i, _)| i)
+                    .collect(),
+                contributors: values.len(),
+            }
+        })
+    }
+
+    fn read_channel<F>(&self, channel: Channel, read: F) -> Result<f64, LinuxI2CError>
+        where F: Fn(&dyn Sensor) -> Result<f64, LinuxI2CError>
+    {
+        let mut values = Vec::new();
+        let mut first_error = None;
+        for (i, sensor) in self.sensors.iter().enumerate() {
+            match read(sensor.as_ref()) {
+                Ok(value) => values.push((i, value)),
+                Err(err) => {
+                    first_error = first_error.or(Some(err));
+                }
+            }
+        }
+        match self.fuse(channel, &values) {
+            Some(fused) => Ok(fused.value),
+            None => Err(first_error.unwrap_or_else(no_sensors)),
+        }
+    }
+}
+
+impl Sensor for SensorFusion {
+    fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
+        self.read_channel(Channel::Temperature, |sensor| sensor.read_temperature())
+    }
+    fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
+        self.read_channel(Channel::Pressure, |sensor| sensor.read_pressure())
+    }
+    fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        self.read_channel(Channel::Humidity, |sensor| sensor.read_humidity())
+    }
+    /// Fails only when no member could be read at all.
+    fn read_measurement(&self) -> Result<Measurement, LinuxI2CError> {
+        let fused = self.read_fused();
+        if fused.temperature.is_none() && fused.pressure.is_none() && fused.humidity.is_none() {
+            return Err(fused.errors.into_iter().map(|(_, err)| err).next().unwrap_or_else(no_sensors));
+        }
+        let mut measurement = Measurement {
+            temperature: fused.temperature.map(|fused| fused.value),
+            pressure: fused.pressure.map(|fused| fused.value),
+            humidity: fused.humidity.map(|fused| fused.value),
+            precision: None,
+            accuracy: None,
+            implausible: Vec::new(),
+        };
+        measurement.accuracy = Some(accuracy::accuracy(&measurement));
+        Ok(measurement)
+    }
+}
+
+/// Combines `values` by `method`; `None` when there are none.
+pub fn combine(method: FusionMethod, values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+    let n = sorted.len();
+    Some(match method {
+             FusionMethod::Mean => mean(&sorted),
+             FusionMethod::Median => {
+                 if n % 2 == 1 {
+                     sorted[n / 2]
+                 } else {
+                     (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+                 }
+             }
+             FusionMethod::TrimmedMean(fraction) => {
+                 let trim = ((n as f64 * fraction.max(0.0)) as usize).min((n - 1) / 2);
+                 mean(&sorted[trim..n - trim])
+             }
+         })
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn no_sensors() -> LinuxI2CError {
+    LinuxI2CError::Io(io::Error::new(io::ErrorKind::NotFound, "no sensors to fuse"))
+}
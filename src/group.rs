@@ -0,0 +1,150 @@
+//! Sampling several sensors together, so that a cycle over many sensors
+//! takes one conversion time rather than one per sensor.
+
+use std::io;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CError;
+
+use super::bme280::Bme280;
+use super::lock::{BusLock, BusLockGuard};
+use super::measurement::Measurement;
+use super::register::Mode;
+
+/// Measurements from every member of a group, taken from conversions that
+/// ran at the same time.
+#[derive(Debug)]
+pub struct GroupMeasurement {
+    /// When the conversions had completed and reading began.
+    pub at: SystemTime,
+    /// One result per member, in the order the members were added.  A
+    /// failing member does not affect the others.
+    pub results: Vec<Result<Measurement, LinuxI2CError>>,
+}
+
+pub struct SensorGroup<T: I2CDevice<Error = LinuxI2CError> + Sized> {
+    sensors: Vec<Bme280<T>>,
+}
+
+impl<T: I2CDevice<Error = LinuxI2CError> + Sized> Default for SensorGroup<T> {
+    fn default() -> SensorGroup<T> {
+        SensorGroup::new()
+    }
+}
+
+impl<T: I2CDevice<Error = LinuxI2CError> + Sized> SensorGroup<T> {
+    pub fn new() -> SensorGroup<T> {
+        SensorGroup { sensors: Vec::new() }
+    }
+
+    /// Adds a sensor and returns its index in every `GroupMeasurement`.
+    /// A sensor in normal mode converts on its own timer and could not be
+    /// triggered with the others, so it is switched to forced mode.
+    pub fn push(&mut self, mut sensor: Bme280<T>) -> Result<usize, LinuxI2CError> {
+        if sensor.mode() == Mode::Normal {
+            try!(sensor.set_mode(Mode::Forced));
+        }
+        self.sensors.push(sensor);
+        Ok(self.sensors.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    pub fn sensors(&self) -> &[Bme280<T>] {
+        &self.sensors
+    }
+
+    pub fn sensors_mut(&mut self) -> &mut [Bme280<T>] {
+        &mut self.sensors
+    }
+
+    pub fn into_inner(self) -> Vec<Bme280<T>> {
+        self.sensors
+    }
+
+    /// Starts a conversion on every member, waits once for the slowest to
+    /// complete, then reads them all.  The members' bus locks are held for
+    /// the whole cycle, so no other process can come between a trigger and
+    /// its read.
+    pub fn read_measurements(&self) -> GroupMeasurement {
+        let (_guards, lock_errors) = self.lock_buses();
+        let started: Vec<Result<(), LinuxI2CError>> = self.sensors
+            .iter()
+            .zip(lock_errors)
+            .map(|(sensor, lock_error)| match lock_error {
+                     Some(err) => Err(err),
+                     None => sensor.start_conversion(),
+                 })
+            .collect();
+        let wait = self.sensors
+            .iter()
+            .zip(started.iter())
+            .filter(|&(sensor, start)| start.is_ok() && sensor.mode() != Mode::Normal)
+            .map(|(sensor, _)| sensor.measurement_time().max)
+            .max()
+            .unwrap_or_else(|| Duration::from_millis(0));
+        thread::sleep(wait);
+
+        let at = SystemTime::now();
+        let results = self.sensors
+            .iter()
+            .zip(started)
+            .map(|(sensor, start)| start.and_then(|_| sensor.read_conversion()))
+            .collect();
+        for sensor in &self.sensors {
+            sensor.set_bus_lock_held(false);
+        }
+        GroupMeasurement { at, results }
+    }
+
+    /// Takes every distinct bus lock of the members, in path order so that
+    /// groups in different processes cannot deadlock.  Returns the guards,
+    /// and per member the error if its lock could not be taken.
+    fn lock_buses(&self) -> (Vec<BusLockGuard>, Vec<Option<LinuxI2CError>>) {
+        let mut locks: Vec<&BusLock> = self.sensors.iter().filter_map(|sensor| sensor.bus_lock()).collect();
+        locks.sort_by(|a, b| a.path().cmp(b.path()));
+        locks.dedup_by(|a, b| a.path() == b.path());
+
+        let mut guards = Vec::new();
+        let mut failures = Vec::new();
+        for lock in locks {
+            match lock.acquire() {
+                Ok(guard) => guards.push(guard),
+                Err(err) => failures.push((lock.path(), err)),
+            }
+        }
+        let errors = self.sensors
+            .iter()
+            .map(|sensor| {
+                let lock = match sensor.bus_lock() {
+                    Some(lock) => lock,
+                    None => return None,
+                };
+                match failures.iter().find(|(path, _)| *path == lock.path()) {
+                    Some((_, err)) => Some(copy_error(err)),
+                    None => {
+                        sensor.set_bus_lock_held(true);
+                        None
+                    }
+                }
+            })
+            .collect();
+        (guards, errors)
+    }
+}
+
+/// Gives each member that shares a failed lock its own copy of the error.
+fn copy_error(err: &LinuxI2CError) -> LinuxI2CError {
+    let kind = match *err {
+        LinuxI2CError::Io(ref io_err) => io_err.kind(),
+        _ => io::ErrorKind::Other,
+    };
+    LinuxI2CError::Io(io::Error::new(kind, format!("cannot take bus lock: {}", err)))
+}
@@ -0,0 +1,110 @@
+//! Detection of sensors whose ADC values have frozen, e.g. because normal
+//! mode was lost or the bus keeps returning cached bytes.  A live sensor's
+//! noise makes bit-identical raw values across many samples essentially
+//! impossible, while the compensated readings of a frozen one look fine.
+
+use std::time::{Duration, Instant};
+
+use super::measurement::Channel;
+
+/// Number of identical raw samples after which a channel counts as stuck,
+/// unless configured otherwise.
+pub const DEFAULT_FLATLINE_SAMPLES: usize = 10;
+
+/// Overall sensor health as judged from the raw values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    /// No channel has flatlined.
+    Healthy,
+    /// The listed channels returned the same raw value for the configured
+    /// number of consecutive samples.
+    Stuck(Vec<Channel>),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Track {
+    last: Option<u32>,
+    repeats: usize,
+    /// When the last latched sample was recorded.
+    at: Option<Instant>,
+}
+
+/// Tracks consecutive identical raw values per channel.
+#[derive(Debug, Clone)]
+pub struct FlatlineDetector {
+    threshold: usize,
+    tracks: [Track; Channel::COUNT],
+}
+
+impl FlatlineDetector {
+    /// Creates a detector that flags a channel after `threshold` identical
+    /// samples in a row.  Thresholds below 2 are raised to 2.
+    pub fn new(threshold: usize) -> FlatlineDetector {
+        FlatlineDetector {
+            threshold: threshold.max(2),
+            tracks: [Track::default(); Channel::COUNT],
+        }
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Records a raw ADC value for `channel` from a fresh conversion.
+    pub fn record(&mut self, channel: Channel, raw: u32) {
+        self.tracks[channel.index()].push(raw);
+    }
+
+    /// Records a raw value read at `at` in normal mode, where reads less
+    /// than a conversion `period` apart may return the same conversion
+    /// again.  Such re-reads are ignored rather than counted as repeats.
+    pub fn record_latched(&mut self, channel: Channel, raw: u32, at: Instant, period: Duration) {
+        let track = &mut self.tracks[channel.index()];
+        if track.at.is_some_and(|last| at.duration_since(last) < period) {
+            return;
+        }
+        track.at = Some(at);
+        track.push(raw);
+    }
+
+    /// Whether `channel` has returned the same raw value for at least the
+    /// threshold number of samples.
+    pub fn is_stuck(&self, channel: Channel) -> bool {
+        self.tracks[channel.index()].repeats >= self.threshold
+    }
+
+    pub fn health(&self) -> Health {
+        let stuck: Vec<Channel> = [Channel::Temperature, Channel::Pressure, Channel::Humidity]
+            .iter()
+            .cloned()
+            .filter(|&channel| self.is_stuck(channel))
+            .collect();
+        if stuck.is_empty() {
+            Health::Healthy
+        } else {
+            Health::Stuck(stuck)
+        }
+    }
+
+    /// Forgets every sample recorded so far.
+    pub fn reset(&mut self) {
+        self.tracks = [Track::default(); Channel::COUNT];
+    }
+}
+
+impl Track {
+    fn push(&mut self, raw: u32) {
+        if self.last == Some(raw) {
+            self.repeats += 1;
+        } else {
+            self.last = Some(raw);
+            self.repeats = 1;
+        }
+    }
+}
+
+impl Default for FlatlineDetector {
+    fn default() -> FlatlineDetector {
+        FlatlineDetector::new(DEFAULT_FLATLINE_SAMPLES)
+    }
+}
@@ -0,0 +1,142 @@
+//! Backend for sensors claimed by the kernel's `bmp280` IIO driver, which
+//! keeps i2c-dev from opening them.  Readings come from the driver's sysfs
+//! attributes instead of the registers.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use i2cdev::linux::LinuxI2CError;
+
+use super::bme280::Sensor;
+use super::error::Bme280Error;
+use super::measurement::{Channel, FAHRENHEIT_PER_CELSIUS, IN_HG_PER_PASCAL};
+use super::register::Oversampling;
+
+/// Where the kernel lists IIO devices.
+pub const SYSFS_IIO_DEVICES: &str = "/sys/bus/iio/devices";
+
+/// Name the IIO driver gives a BME280.
+const IIO_NAME: &str = "bme280";
+
+/// A BME280 read through the IIO device directory, e.g.
+/// `/sys/bus/iio/devices/iio:device0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IioBme280 {
+    dir: PathBuf,
+}
+
+impl IioBme280 {
+    /// Uses the IIO device directory `dir`.
+    pub fn new<P: AsRef<Path>>(dir: P) -> IioBme280 {
+        IioBme280 { dir: dir.as_ref().to_path_buf() }
+    }
+
+    /// Finds the first BME280 bound to the IIO driver.
+    pub fn find() -> Result<IioBme280, LinuxI2CError> {
+        IioBme280::find_in(Path::new(SYSFS_IIO_DEVICES))
+    }
+
+    /// Like `find`, but searches `sysfs_devices` instead of the real sysfs.
+    pub fn find_in(sysfs_devices: &Path) -> Result<IioBme280, LinuxI2CError> {
+        let mut dirs = Vec::new();
+        for entry in try!(fs::read_dir(sysfs_devices)) {
+            let path = try!(entry).path();
+            let is_device = path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("iio:device"));
+            if is_device {
+                dirs.push(path);
+            }
+        }
+        dirs.sort();
+        for dir in dirs {
+            if let Ok(name) = fs::read_to_string(dir.join("name")) {
+                if name.trim() == IIO_NAME {
+                    return Ok(IioBme280::new(dir));
+                }
+            }
+        }
+        Err(Bme280Error::NoIioDevice.into())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Reads the channel's oversampling from its `*_oversampling_ratio`
+    /// attribute.
+    pub fn oversampling(&self, channel: Channel) -> Result<Oversampling, LinuxI2CError> {
+        let ratio = try!(self.read_value(&format!("{}_oversampling_ratio", prefix(channel))));
+        match ratio as u32 {
+            1 => Ok(Oversampling::X1),
+            2 => Ok(Oversampling::X2),
+            4 => Ok(Oversampling::X4),
+            8 => Ok(Oversampling::X8),
+            16 => Ok(Oversampling::X16),
+            _ => Err(invalid_data(format!("unexpected oversampling ratio {}", ratio))),
+        }
+    }
+
+    /// Sets the channel's oversampling.  The driver cannot skip channels,
+    /// so `Oversampling::Skipped` is refused.
+    pub fn set_oversampling(&self, channel: Channel, oversampling: Oversampling) -> Result<(), LinuxI2CError> {
+        if oversampling == Oversampling::Skipped {
+            return Err(Bme280Error::Unsupported("skipping a channel through IIO").into());
+        }
+        let path = self.dir.join(format!("{}_oversampling_ratio", prefix(channel)));
+        try!(fs::write(path, oversampling.factor().to_string()));
+        Ok(())
+    }
+
+    /// Reads the current Fahrenheit temperature value from the sensor
+    pub fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
+        // The driver reports millidegrees Celsius:
+        let millidegrees = try!(self.read_value("in_temp_input"));
+        Ok(millidegrees / 1000.0 * FAHRENHEIT_PER_CELSIUS + 32.0)
+    }
+
+    /// Reads the current barometric pressure in InHg from the sensor
+    pub fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
+        // The driver reports kilopascals:
+        let kilopascals = try!(self.read_value("in_pressure_input"));
+        Ok(kilopascals * 1000.0 * IN_HG_PER_PASCAL)
+    }
+
+    pub fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        // The driver reports thousandths of a percent:
+        let millipercent = try!(self.read_value("in_humidityrelative_input"));
+        Ok(millipercent / 1000.0)
+    }
+
+    fn read_value(&self, attribute: &str) -> Result<f64, LinuxI2CError> {
+        let text = try!(fs::read_to_string(self.dir.join(attribute)));
+        text.trim()
+            .parse()
+            .map_err(|_| invalid_data(format!("unreadable {}: {:?}", attribute, text)))
+    }
+}
+
+impl Sensor for IioBme280 {
+    fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
+        self.read_temperature()
+    }
+    fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
+        self.read_pressure()
+    }
+    fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        self.read_humidity()
+    }
+}
+
+/// Prefix of the channel's IIO attributes.
+fn prefix(channel: Channel) -> &'static str {
+    match channel {
+        Channel::Temperature => "in_temp",
+        Channel::Pressure => "in_pressure",
+        Channel::Humidity => "in_humidityrelative",
+    }
+}
+
+fn invalid_data(message: String) -> LinuxI2CError {
+    LinuxI2CError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+}
@@ -2,5 +2,28 @@ extern crate i2cdev;
 extern crate nix;
 
 mod calibration;
+pub mod error;
+pub mod measurement;
 pub mod register;
-pub mod bme280;
\ No newline at end of file
+pub mod bme280;
+pub mod typestate;
+pub mod timing;
+pub mod preset;
+pub mod precision;
+pub mod accuracy;
+pub mod plausibility;
+pub mod retry;
+pub mod resilient;
+pub mod watchdog;
+pub mod health;
+pub mod address;
+pub mod bus;
+pub mod discovery;
+pub mod mux;
+pub mod shared_bus;
+pub mod lock;
+pub mod iio;
+pub mod driver;
+pub mod group;
+pub mod fusion;
+pub mod differential;
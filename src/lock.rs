@@ -0,0 +1,89 @@
+//! Advisory locking that keeps separate processes from interleaving their
+//! transactions with the same sensor, e.g. one process's forced-mode
+//! trigger landing between another's trigger and data read.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use i2cdev::linux::LinuxI2CError;
+use nix::libc;
+
+use super::address::Bme280Address;
+
+/// Directory lock files go in unless told otherwise.
+pub const DEFAULT_LOCK_DIR: &str = "/var/lock";
+
+/// A lock file shared by every process using the same sensor.  Locking
+/// uses `flock`, so the lock is released when its holder exits, however
+/// that happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusLock {
+    path: PathBuf,
+}
+
+impl BusLock {
+    /// Uses the lock file at `path`, which is created if needed.
+    pub fn new<P: AsRef<Path>>(path: P) -> BusLock {
+        BusLock { path: path.as_ref().to_path_buf() }
+    }
+
+    /// Uses the lock file in `DEFAULT_LOCK_DIR` for the sensor at `address`
+    /// on the bus device at `bus`.
+    pub fn for_device<P: AsRef<Path>>(bus: P, address: Bme280Address) -> BusLock {
+        BusLock::for_device_in(DEFAULT_LOCK_DIR, bus, address)
+    }
+
+    /// Like `for_device`, but keeps the lock file in `dir`.
+    pub fn for_device_in<D, P>(dir: D, bus: P, address: Bme280Address) -> BusLock
+        where D: AsRef<Path>,
+              P: AsRef<Path>
+    {
+        // Resolve udev aliases such as /dev/i2c-sensors, so that processes
+        // naming the bus differently still share a lock:
+        let bus = fs::canonicalize(bus.as_ref()).unwrap_or_else(|_| bus.as_ref().to_path_buf());
+        let bus_name = bus.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file_name = format!("bme280-{}-{:02x}.lock", bus_name, address.value());
+        BusLock::new(dir.as_ref().join(file_name))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Waits until no other holder has the lock, then takes it until the
+    /// returned guard is dropped.
+    pub fn acquire(&self) -> Result<BusLockGuard, LinuxI2CError> {
+        let file = try!(OpenOptions::new()
+                            .read(true)
+                            .write(true)
+                            .create(true)
+                            .truncate(false)
+                            .open(&self.path));
+        loop {
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+                return Ok(BusLockGuard { _file: file });
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(LinuxI2CError::Io(err));
+            }
+        }
+    }
+}
+
+/// Holds a `BusLock` until dropped; closing the file releases the lock.
+pub struct BusLockGuard {
+    _file: File,
+}
+
+/// Locks a mutex shared by the proxies of one bus, taking it over even if
+/// a thread panicked while holding it: every transaction starts by putting
+/// the bus in the state it needs (multiplexer channel, slave address), so a
+/// half-finished one leaves nothing behind to corrupt the next.
+pub(crate) fn lock_shared<M>(mutex: &Mutex<M>) -> MutexGuard<'_, M> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,58 @@
+use std::fmt::Display;
+use std::fmt::Result;
+use std::fmt::Formatter;
+
+use super::accuracy::MeasurementAccuracy;
+use super::precision::Precision;
+
+/// Conversion factor from Pascals to inches of mercury.
+pub const IN_HG_PER_PASCAL: f64 = 0.000295299830714;
+
+/// Size of one Celsius degree in Fahrenheit degrees.
+pub const FAHRENHEIT_PER_CELSIUS: f64 = 1.8;
+
+/// The sensor's three measurement channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Temperature,
+    Pressure,
+    Humidity,
+}
+
+impl Channel {
+    /// Number of channels, for tables indexed by `Channel::index`.
+    pub const COUNT: usize = 3;
+
+    /// Position of the channel in register order, from 0 to `COUNT - 1`.
+    pub fn index(self) -> usize {
+        match self {
+            Channel::Temperature => 0,
+            Channel::Pressure => 1,
+            Channel::Humidity => 2,
+        }
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Compensated values from a single conversion, in the same units as the
+/// individual `read_*` methods.  A channel is `None` when it is disabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    /// Degrees Fahrenheit
+    pub temperature: Option<f64>,
+    /// InHg
+    pub pressure: Option<f64>,
+    /// Percent relative humidity
+    pub humidity: Option<f64>,
+    /// Expected resolution and noise of the values above, when known.
+    pub precision: Option<Precision>,
+    /// Datasheet accuracy bounds of the values above.
+    pub accuracy: Option<MeasurementAccuracy>,
+    /// Channels that failed plausibility checks, when flagging is enabled.
+    pub implausible: Vec<Channel>,
+}
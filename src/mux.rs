@@ -0,0 +1,164 @@
+//! Support for sensors behind a TCA9548A / PCA9548 I2C multiplexer, which
+//! connects its upstream bus to any of eight downstream channels.  As each
+//! BME280 only offers two addresses, this is how more of them share a bus.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+
+use super::address::Bme280Address;
+use super::bme280::Bme280;
+use super::error::Bme280Error;
+use super::lock;
+
+/// Number of downstream channels on the multiplexer.
+pub const MUX_CHANNELS: u8 = 8;
+
+/// Handle to a multiplexer.  Clones refer to the same multiplexer, whose
+/// control device is locked for every transaction through any channel, so
+/// sensors behind it may be used from several threads.
+pub struct Tca9548a<M: I2CDevice<Error = LinuxI2CError>> {
+    control: Arc<Mutex<M>>,
+}
+
+impl<M: I2CDevice<Error = LinuxI2CError>> Clone for Tca9548a<M> {
+    fn clone(&self) -> Tca9548a<M> {
+        Tca9548a { control: self.control.clone() }
+    }
+}
+
+impl Tca9548a<LinuxI2CDevice> {
+    /// Opens the multiplexer at `address`, 0x70 to 0x77 depending on its
+    /// A0-A2 pins, on the bus device at `path`.
+    pub fn open<P: AsRef<Path>>(path: P, address: u16) -> Result<Tca9548a<LinuxI2CDevice>, LinuxI2CError> {
+        Ok(Tca9548a::new(try!(LinuxI2CDevice::new(path, address))))
+    }
+}
+
+impl<M: I2CDevice<Error = LinuxI2CError>> Tca9548a<M> {
+    /// Wraps the device that talks to the multiplexer itself.
+    pub fn new(control: M) -> Tca9548a<M> {
+        Tca9548a { control: Arc::new(Mutex::new(control)) }
+    }
+
+    /// Returns `device`, which must be reachable through downstream
+    /// `channel`, wrapped so that the channel is selected before each of
+    /// its transactions.
+    pub fn channel<T>(&self, channel: u8, device: T) -> Result<MuxChannel<M, T>, LinuxI2CError>
+        where T: I2CDevice<Error = LinuxI2CError>
+    {
+        if channel >= MUX_CHANNELS {
+            return Err(Bme280Error::InvalidMuxChannel(channel).into());
+        }
+        Ok(MuxChannel {
+               control: self.control.clone(),
+               channel,
+               device,
+           })
+    }
+}
+
+impl Bme280<MuxChannel<LinuxI2CDevice, LinuxI2CDevice>> {
+    /// Opens the sensor at `address` behind downstream `channel` of `mux`,
+    /// where `path` is the bus device the multiplexer sits on.
+    pub fn open_on_mux<P: AsRef<Path>>(path: P,
+                                       mux: &Tca9548a<LinuxI2CDevice>,
+                                       channel: u8,
+                                       address: Bme280Address)
+                                       -> Result<Bme280<MuxChannel<LinuxI2CDevice, LinuxI2CDevice>>, LinuxI2CError> {
+        let device = try!(LinuxI2CDevice::new(path, address.value()));
+        Bme280::new_from_device(try!(mux.channel(channel, device)))
+    }
+}
+
+/// A device behind one downstream channel of a multiplexer.
+pub struct MuxChannel<M: I2CDevice<Error = LinuxI2CError>, T: I2CDevice<Error = LinuxI2CError>> {
+    control: Arc<Mutex<M>>,
+    channel: u8,
+    device: T,
+}
+
+impl<M, T> MuxChannel<M, T>
+    where M: I2CDevice<Error = LinuxI2CError>,
+          T: I2CDevice<Error = LinuxI2CError>
+{
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Runs `op` with the multiplexer locked and switched to this channel.
+    /// The channel is selected every time, rather than remembered, so that
+    /// a multiplexer that was reset or switched by another process is
+    /// put right again.
+    fn run<R, F>(&mut self, op: F) -> Result<R, LinuxI2CError>
+        where F: FnOnce(&mut T) -> Result<R, LinuxI2CError>
+    {
+        let mut control = lock::lock_shared(&self.control);
+        try!(control.smbus_write_byte(1 << self.channel));
+        op(&mut self.device)
+    }
+}
+
+impl<M, T> I2CDevice for MuxChannel<M, T>
+    where M: I2CDevice<Error = LinuxI2CError>,
+          T: I2CDevice<Error = LinuxI2CError>
+{
+    type Error = LinuxI2CError;
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.run(|dev| dev.read(data))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.run(|dev| dev.write(data))
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_quick(bit))
+    }
+
+    fn smbus_read_byte(&mut self) -> Result<u8, Self::Error> {
+        self.run(|dev| dev.smbus_read_byte())
+    }
+
+    fn smbus_write_byte(&mut self, value: u8) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_byte(value))
+    }
+
+    fn smbus_read_byte_data(&mut self, register: u8) -> Result<u8, Self::Error> {
+        self.run(|dev| dev.smbus_read_byte_data(register))
+    }
+
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_byte_data(register, value))
+    }
+
+    fn smbus_read_word_data(&mut self, register: u8) -> Result<u16, Self::Error> {
+        self.run(|dev| dev.smbus_read_word_data(register))
+    }
+
+    fn smbus_write_word_data(&mut self, register: u8, value: u16) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_word_data(register, value))
+    }
+
+    fn smbus_process_word(&mut self, register: u8, value: u16) -> Result<u16, Self::Error> {
+        self.run(|dev| dev.smbus_process_word(register, value))
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>, Self::Error> {
+        self.run(|dev| dev.smbus_read_block_data(register))
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>, Self::Error> {
+        self.run(|dev| dev.smbus_read_i2c_block_data(register, len))
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_block_data(register, values))
+    }
+
+    fn smbus_process_block(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_process_block(register, values))
+    }
+}
@@ -0,0 +1,39 @@
+//! Checks that catch bus glitches, such as all-0xFF or all-zero reads, and
+//! values outside the sensor's operating range.
+
+use super::measurement::{Channel, FAHRENHEIT_PER_CELSIUS, IN_HG_PER_PASCAL};
+
+/// How the driver treats samples that fail plausibility checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Plausibility {
+    /// Accept every sample.
+    Off,
+    /// Keep implausible samples but list the affected channels in
+    /// `Measurement::implausible`.  Single-channel reads have nowhere to
+    /// carry the flag and behave as with `Off`.
+    Flag,
+    /// Fail the read with `Bme280Error::Implausible`.
+    Reject,
+}
+
+/// Whether a raw ADC value could have come from a working sensor.  Values
+/// with every bit set or cleared are what a stuck or floating bus returns.
+pub fn raw_plausible(channel: Channel, raw: u32) -> bool {
+    let all_ones = match channel {
+        Channel::Temperature | Channel::Pressure => 0xFFFFF,
+        Channel::Humidity => 0xFFFF,
+    };
+    raw != 0 && raw != all_ones
+}
+
+/// Whether a compensated value, in the same units as the readings, lies
+/// within the operating range: -40..85 C, 300..1100 hPa and 0..100 %RH.
+pub fn in_operating_range(channel: Channel, value: f64) -> bool {
+    let (min, max) = match channel {
+        Channel::Temperature => (-40.0 * FAHRENHEIT_PER_CELSIUS + 32.0,
+                                 85.0 * FAHRENHEIT_PER_CELSIUS + 32.0),
+        Channel::Pressure => (30000.0 * IN_HG_PER_PASCAL, 110000.0 * IN_HG_PER_PASCAL),
+        Channel::Humidity => (0.0, 100.0),
+    };
+    value >= min && value <= max
+}
@@ -0,0 +1,87 @@
+//! Expected output resolution and RMS noise for a given configuration,
+//! taken from the datasheet's resolution and noise tables.  Figures are in
+//! the same units as the readings (Fahrenheit, InHg and %RH) so they can
+//! be compared directly against changes in value.
+
+use super::bme280::Settings;
+use super::measurement::{FAHRENHEIT_PER_CELSIUS, IN_HG_PER_PASCAL};
+use super::register::{Filter, Oversampling};
+
+/// Smallest step and RMS noise of a single channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelPrecision {
+    pub resolution: f64,
+    pub noise: f64,
+}
+
+/// Precision of each channel.  A channel is `None` when it is disabled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Precision {
+    pub temperature: Option<ChannelPrecision>,
+    pub pressure: Option<ChannelPrecision>,
+    pub humidity: Option<ChannelPrecision>,
+}
+
+/// Pressure RMS noise in Pa, by oversampling (rows, x1 to x16) and IIR
+/// filter coefficient (columns, off to 16).
+const PRESSURE_NOISE: [[f64; 5]; 5] = [[3.3, 1.9, 1.2, 0.9, 0.4],
+                                       [2.6, 1.5, 1.0, 0.6, 0.4],
+                                       [2.1, 1.2, 0.8, 0.5, 0.3],
+                                       [1.6, 1.0, 0.6, 0.4, 0.2],
+                                       [1.3, 0.8, 0.5, 0.4, 0.2]];
+
+/// Pressure resolution in Pa, by oversampling (16 to 20 bit).
+const PRESSURE_RESOLUTION: [f64; 5] = [2.62, 1.31, 0.66, 0.33, 0.16];
+
+/// Temperature RMS noise in Celsius, by oversampling.
+const TEMPERATURE_NOISE: [f64; 5] = [0.005, 0.004, 0.003, 0.003, 0.002];
+
+/// Temperature resolution in Celsius, by oversampling (16 to 20 bit).
+const TEMPERATURE_RESOLUTION: [f64; 5] = [0.0050, 0.0025, 0.0012, 0.0006, 0.0003];
+
+/// Humidity RMS noise in %RH, by oversampling.  The datasheet gives 0.07
+/// at x1 and 0.02 at x16; the steps between fall off with the square root
+/// of the sample count.
+const HUMIDITY_NOISE: [f64; 5] = [0.07, 0.05, 0.035, 0.025, 0.02];
+
+/// Humidity output is always 16 bit.
+const HUMIDITY_RESOLUTION: f64 = 0.008;
+
+/// Computes the expected precision of each channel under `settings`.
+pub fn precision(settings: &Settings) -> Precision {
+    // With the IIR filter on, temperature and pressure are output at the
+    // full 20 bit regardless of oversampling:
+    let filtered = settings.filter != Filter::Off;
+    let resolution_row = |i: usize| if filtered { 4 } else { i };
+
+    Precision {
+        temperature: row(settings.osrs_t).map(|i| {
+            ChannelPrecision {
+                resolution: TEMPERATURE_RESOLUTION[resolution_row(i)] *
+                            FAHRENHEIT_PER_CELSIUS,
+                noise: TEMPERATURE_NOISE[i] * FAHRENHEIT_PER_CELSIUS,
+            }
+        }),
+        pressure: row(settings.osrs_p).map(|i| {
+            ChannelPrecision {
+                resolution: PRESSURE_RESOLUTION[resolution_row(i)] *
+                            IN_HG_PER_PASCAL,
+                noise: PRESSURE_NOISE[i][settings.filter as usize] * IN_HG_PER_PASCAL,
+            }
+        }),
+        humidity: row(settings.osrs_h).map(|i| {
+            ChannelPrecision {
+                resolution: HUMIDITY_RESOLUTION,
+                noise: HUMIDITY_NOISE[i],
+            }
+        }),
+    }
+}
+
+/// Index into the tables above for an oversampling setting.
+fn row(osrs: Oversampling) -> Option<usize> {
+    match osrs {
+        Oversampling::Skipped => None,
+        _ => Some(osrs as usize - 1),
+    }
+}
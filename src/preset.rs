@@ -0,0 +1,110 @@
+//! The operating modes recommended in section 3.5 of the datasheet.
+
+use super::bme280::Settings;
+use super::register::{Filter, Mode, Oversampling, Standby};
+use super::timing;
+
+/// A recommended combination of oversampling, filter, mode and standby
+/// settings for a typical use case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Forced mode, one sample per minute, no filtering.
+    WeatherMonitoring,
+    /// Forced mode, one sample per second, pressure disabled.
+    HumiditySensing,
+    /// Normal mode with heavy pressure oversampling and filtering.
+    IndoorNavigation,
+    /// Normal mode with fast pressure updates, humidity disabled.
+    Gaming,
+}
+
+impl Preset {
+    pub fn settings(&self) -> Settings {
+        match *self {
+            Preset::WeatherMonitoring => {
+                Settings {
+                    osrs_t: Oversampling::X1,
+                    osrs_p: Oversampling::X1,
+                    osrs_h: Oversampling::X1,
+                    filter: Filter::Off,
+                    standby: Standby::Ms0_5,
+                }
+            }
+            Preset::HumiditySensing => {
+                Settings {
+                    osrs_t: Oversampling::X1,
+                    osrs_p: Oversampling::Skipped,
+                    osrs_h: Oversampling::X1,
+                    filter: Filter::Off,
+                    standby: Standby::Ms0_5,
+                }
+            }
+            Preset::IndoorNavigation => {
+                Settings {
+                    osrs_t: Oversampling::X2,
+                    osrs_p: Oversampling::X16,
+                    osrs_h: Oversampling::X1,
+                    filter: Filter::X16,
+                    standby: Standby::Ms0_5,
+                }
+            }
+            Preset::Gaming => {
+                Settings {
+                    osrs_t: Oversampling::X1,
+                    osrs_p: Oversampling::X4,
+                    osrs_h: Oversampling::Skipped,
+                    filter: Filter::X16,
+                    standby: Standby::Ms0_5,
+                }
+            }
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        match *self {
+            Preset::WeatherMonitoring | Preset::HumiditySensing => Mode::Forced,
+            Preset::IndoorNavigation | Preset::Gaming => Mode::Normal,
+        }
+    }
+
+    /// Typical current draw in microamps at the recommended data rate.
+    pub fn current_draw(&self) -> f64 {
+        match *self {
+            Preset::WeatherMonitoring => 0.16,
+            Preset::HumiditySensing => 2.9,
+            Preset::IndoorNavigation => 633.0,
+            Preset::Gaming => 581.0,
+        }
+    }
+
+    /// Samples per second.  For the forced-mode presets this is the rate the
+    /// datasheet recommends triggering conversions at.
+    pub fn data_rate(&self) -> f64 {
+        match *self {
+            Preset::WeatherMonitoring => 1.0 / 60.0,
+            Preset::HumiditySensing => 1.0,
+            Preset::IndoorNavigation | Preset::Gaming => {
+                let s = self.settings();
+                timing::output_data_rate(s.osrs_t, s.osrs_p, s.osrs_h, s.standby)
+            }
+        }
+    }
+
+    /// RMS pressure noise in Pa, or `None` when pressure is disabled.
+    pub fn pressure_noise(&self) -> Option<f64> {
+        match *self {
+            Preset::WeatherMonitoring => Some(3.3),
+            Preset::HumiditySensing => None,
+            Preset::IndoorNavigation => Some(0.2),
+            Preset::Gaming => Some(0.3),
+        }
+    }
+
+    /// RMS humidity noise in %RH, or `None` when humidity is disabled.
+    pub fn humidity_noise(&self) -> Option<f64> {
+        match *self {
+            Preset::Gaming => None,
+            _ => Some(0.07),
+        }
+    }
+}
@@ -1,9 +1,10 @@
+use std::convert::TryFrom;
 use std::fmt::Display;
 use std::fmt::Result;
 use std::fmt::Formatter;
 
 /// Enum mapping sensor hex addresses to human-readable values.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Register {
     T1 = 0x88,
     T2 = 0x8A,
@@ -32,6 +33,7 @@ pub enum Register {
     SoftReset = 0xE0,
 
     ControlHum = 0xF2,
+    Status = 0xF3,
     Control = 0xF4,
     Config = 0xF5,
     PressureData = 0xF7,
@@ -51,3 +53,290 @@ impl Display for Register {
         // fmt::Debug::fmt(self, f)
     }
 }
+
+impl TryFrom<u8> for Register {
+    /// The address that did not map to a known register.
+    type Error = u8;
+
+    fn try_from(address: u8) -> ::std::result::Result<Register, u8> {
+        match address {
+            0x88 => Ok(Register::T1),
+            0x8A => Ok(Register::T2),
+            0x8C => Ok(Register::T3),
+
+            0x8E => Ok(Register::P1),
+            0x90 => Ok(Register::P2),
+            0x92 => Ok(Register::P3),
+            0x94 => Ok(Register::P4),
+            0x96 => Ok(Register::P5),
+            0x98 => Ok(Register::P6),
+            0x9A => Ok(Register::P7),
+            0x9C => Ok(Register::P8),
+            0x9E => Ok(Register::P9),
+
+            0xA1 => Ok(Register::H1),
+            0xE1 => Ok(Register::H2),
+            0xE3 => Ok(Register::H3),
+            0xE4 => Ok(Register::H4),
+            0xE5 => Ok(Register::H5),
+            0xE6 => Ok(Register::H6),
+            0xE7 => Ok(Register::H7),
+
+            0xD0 => Ok(Register::ChipId),
+            0xD1 => Ok(Register::Version),
+            0xE0 => Ok(Register::SoftReset),
+
+            0xF2 => Ok(Register::ControlHum),
+            0xF3 => Ok(Register::Status),
+            0xF4 => Ok(Register::Control),
+            0xF5 => Ok(Register::Config),
+            0xF7 => Ok(Register::PressureData),
+            0xF8 => Ok(Register::PressureData1),
+            0xF9 => Ok(Register::PressureData2),
+            0xFA => Ok(Register::TemperatureData),
+            0xFB => Ok(Register::TemperatureData1),
+            0xFC => Ok(Register::TemperatureData2),
+            0xFD => Ok(Register::HumidityData),
+            0xFE => Ok(Register::HumidityData1),
+            x => Err(x),
+        }
+    }
+}
+
+/// Sensor power modes, as encoded in the low two bits of the Control register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Sleep = 0b00,
+    Forced = 0b01,
+    Normal = 0b11,
+}
+
+impl From<u8> for Mode {
+    fn from(bits: u8) -> Mode {
+        // Both 01 and 10 select forced mode.
+        match bits & 0b11 {
+            0b00 => Mode::Sleep,
+            0b11 => Mode::Normal,
+            _ => Mode::Forced,
+        }
+    }
+}
+
+/// Per-channel oversampling, as encoded in the osrs_x fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oversampling {
+    Skipped = 0b000,
+    X1 = 0b001,
+    X2 = 0b010,
+    X4 = 0b011,
+    X8 = 0b100,
+    X16 = 0b101,
+}
+
+impl Oversampling {
+    /// Number of samples averaged per conversion; zero when skipped.
+    pub fn factor(&self) -> u8 {
+        match *self {
+            Oversampling::Skipped => 0,
+            Oversampling::X1 => 1,
+            Oversampling::X2 => 2,
+            Oversampling::X4 => 4,
+            Oversampling::X8 => 8,
+            Oversampling::X16 => 16,
+        }
+    }
+}
+
+impl From<u8> for Oversampling {
+    fn from(bits: u8) -> Oversampling {
+        // Every encoding above 101 also means 16x.
+        match bits & 0b111 {
+            0b000 => Oversampling::Skipped,
+            0b001 => Oversampling::X1,
+            0b010 => Oversampling::X2,
+            0b011 => Oversampling::X4,
+            0b100 => Oversampling::X8,
+            _ => Oversampling::X16,
+        }
+    }
+}
+
+/// IIR filter coefficient, as encoded in the filter field of the Config register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Off = 0b000,
+    X2 = 0b001,
+    X4 = 0b010,
+    X8 = 0b011,
+    X16 = 0b100,
+}
+
+impl From<u8> for Filter {
+    fn from(bits: u8) -> Filter {
+        match bits & 0b111 {
+            0b000 => Filter::Off,
+            0b001 => Filter::X2,
+            0b010 => Filter::X4,
+            0b011 => Filter::X8,
+            _ => Filter::X16,
+        }
+    }
+}
+
+/// Inactive time between conversions in normal mode, as encoded in the
+/// t_sb field of the Config register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Standby {
+    Ms0_5 = 0b000,
+    Ms62_5 = 0b001,
+    Ms125 = 0b010,
+    Ms250 = 0b011,
+    Ms500 = 0b100,
+    Ms1000 = 0b101,
+    Ms10 = 0b110,
+    Ms20 = 0b111,
+}
+
+impl From<u8> for Standby {
+    fn from(bits: u8) -> Standby {
+        match bits & 0b111 {
+            0b000 => Standby::Ms0_5,
+            0b001 => Standby::Ms62_5,
+            0b010 => Standby::Ms125,
+            0b011 => Standby::Ms250,
+            0b100 => Standby::Ms500,
+            0b101 => Standby::Ms1000,
+            0b110 => Standby::Ms10,
+            _ => Standby::Ms20,
+        }
+    }
+}
+
+/// Implemented by the typed views of the sensor's control and status registers.
+pub trait Bitfield: From<u8> + Into<u8> + Copy {
+    /// The register this bitfield describes.
+    const REGISTER: Register;
+    /// Bits with a defined meaning; all others are reserved and must be
+    /// preserved when writing.
+    const MASK: u8;
+}
+
+/// Marker for bitfields that may be written back to the sensor.
+pub trait Writable: Bitfield {}
+
+/// Humidity oversampling (register 0xF2).  Only takes effect after a
+/// subsequent write to `CtrlMeas`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CtrlHum {
+    pub osrs_h: Oversampling,
+}
+
+impl From<u8> for CtrlHum {
+    fn from(bits: u8) -> CtrlHum {
+        CtrlHum { osrs_h: Oversampling::from(bits) }
+    }
+}
+
+impl From<CtrlHum> for u8 {
+    fn from(reg: CtrlHum) -> u8 {
+        reg.osrs_h as u8
+    }
+}
+
+impl Bitfield for CtrlHum {
+    const REGISTER: Register = Register::ControlHum;
+    const MASK: u8 = 0b0000_0111;
+}
+
+impl Writable for CtrlHum {}
+
+/// Temperature and pressure oversampling plus power mode (register 0xF4).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CtrlMeas {
+    pub osrs_t: Oversampling,
+    pub osrs_p: Oversampling,
+    pub mode: Mode,
+}
+
+impl From<u8> for CtrlMeas {
+    fn from(bits: u8) -> CtrlMeas {
+        CtrlMeas {
+            osrs_t: Oversampling::from(bits >> 5),
+            osrs_p: Oversampling::from(bits >> 2),
+            mode: Mode::from(bits),
+        }
+    }
+}
+
+impl From<CtrlMeas> for u8 {
+    fn from(reg: CtrlMeas) -> u8 {
+        (reg.osrs_t as u8) << 5 | (reg.osrs_p as u8) << 2 | reg.mode as u8
+    }
+}
+
+impl Bitfield for CtrlMeas {
+    const REGISTER: Register = Register::Control;
+    const MASK: u8 = 0b1111_1111;
+}
+
+impl Writable for CtrlMeas {}
+
+/// Standby time, IIR filter and SPI 3-wire selection (register 0xF5).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub t_sb: Standby,
+    pub filter: Filter,
+    pub spi3w_en: bool,
+}
+
+impl From<u8> for Config {
+    fn from(bits: u8) -> Config {
+        Config {
+            t_sb: Standby::from(bits >> 5),
+            filter: Filter::from(bits >> 2),
+            spi3w_en: bits & 0b1 != 0,
+        }
+    }
+}
+
+impl From<Config> for u8 {
+    fn from(reg: Config) -> u8 {
+        (reg.t_sb as u8) << 5 | (reg.filter as u8) << 2 | reg.spi3w_en as u8
+    }
+}
+
+impl Bitfield for Config {
+    const REGISTER: Register = Register::Config;
+    const MASK: u8 = 0b1111_1101;
+}
+
+impl Writable for Config {}
+
+/// Conversion and NVM copy progress (register 0xF3).  Read-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// Set while a conversion is running.
+    pub measuring: bool,
+    /// Set while calibration data is being copied from NVM.
+    pub im_update: bool,
+}
+
+impl From<u8> for Status {
+    fn from(bits: u8) -> Status {
+        Status {
+            measuring: bits & 0b1000 != 0,
+            im_update: bits & 0b0001 != 0,
+        }
+    }
+}
+
+impl From<Status> for u8 {
+    fn from(reg: Status) -> u8 {
+        (reg.measuring as u8) << 3 | reg.im_update as u8
+    }
+}
+
+impl Bitfield for Status {
+    const REGISTER: Register = Register::Status;
+    const MASK: u8 = 0b0000_1001;
+}
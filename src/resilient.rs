@@ -0,0 +1,253 @@
+//! Wrapper that survives the sensor or its bus adapter going away, e.g. a
+//! USB-I2C adapter being re-enumerated or the sensor being power-cycled.
+
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::time::{Duration, Instant, SystemTime};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+
+use super::bme280::{Bme280, ConfigurationState, Sensor, Settings};
+use super::error::Bme280Error;
+use super::measurement::Measurement;
+use super::plausibility::Plausibility;
+use super::preset::Preset;
+use super::register::Mode;
+
+/// Why the wrapper reopened the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconnectReason {
+    /// There was no open device, e.g. because an earlier reconnect failed.
+    Disconnected,
+    /// A transaction failed; holds the error message.
+    BusError(String),
+    /// The device stopped identifying itself as a BME280.
+    WrongChip(u8),
+    /// The control registers no longer hold the configured values,
+    /// typically because the sensor was reset.
+    ConfigurationLost,
+}
+
+/// A reconnect attempt, successful or not.
+#[derive(Debug, Clone)]
+pub struct ReconnectEvent {
+    pub reason: ReconnectReason,
+    pub at: SystemTime,
+    pub succeeded: bool,
+}
+
+/// How often the wrapper checks the sensor's configuration unless told
+/// otherwise.
+pub const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A `Bme280` that, after any failed read and every so often between reads,
+/// checks that it is still talking to a correctly configured sensor.  If
+/// not, it reopens the device, re-reads the calibration and reapplies the
+/// settings.
+pub struct ResilientBme280<T: I2CDevice<Error = LinuxI2CError> + Sized> {
+    open: RefCell<Box<dyn FnMut() -> Result<T, LinuxI2CError>>>,
+    bme: RefCell<Option<Bme280<T>>>,
+    settings: Settings,
+    mode: Mode,
+    plausibility: Plausibility,
+    check_interval: Option<Duration>,
+    last_check: Cell<Instant>,
+    events: RefCell<Vec<ReconnectEvent>>,
+    reconnects: Cell<usize>,
+}
+
+impl ResilientBme280<LinuxI2CDevice> {
+    /// Opens the sensor at `i2c_addr` on `/dev/i2c-<bus_num>`, reopening
+    /// that same path whenever the sensor is lost.
+    pub fn open(i2c_addr: u16, bus_num: u8) -> Result<ResilientBme280<LinuxI2CDevice>, LinuxI2CError> {
+        let dev_name = format!("/dev/i2c-{}", bus_num);
+        ResilientBme280::new(move || LinuxI2CDevice::new(&dev_name, i2c_addr))
+    }
+}
+
+impl<T: I2CDevice<Error = LinuxI2CError> + Sized> ResilientBme280<T> {
+    /// Creates the wrapper and connects right away.  `open` is called
+    /// again for every reconnect.  The sensor keeps the settings and power
+    /// mode `Bme280` starts it with until told otherwise.
+    pub fn new<F>(open: F) -> Result<ResilientBme280<T>, LinuxI2CError>
+        where F: FnMut() -> Result<T, LinuxI2CError> + 'static
+    {
+        let mut open = open;
+        let bme = try!(Bme280::new_from_device(try!(open())));
+        if let ConfigurationState::WrongChip(id) = try!(bme.check_configuration()) {
+            return Err(Bme280Error::WrongChip(id).into());
+        }
+        Ok(ResilientBme280 {
+               open: RefCell::new(Box::new(open)),
+               settings: bme.settings(),
+               mode: bme.mode(),
+               bme: RefCell::new(Some(bme)),
+               plausibility: Plausibility::Off,
+               check_interval: Some(DEFAULT_CHECK_INTERVAL),
+               last_check: Cell::new(Instant::now()),
+               events: RefCell::new(Vec::new()),
+               reconnects: Cell::new(0),
+           })
+    }
+
+    /// Sets how long reads may go on before the configuration is checked
+    /// again, even though none of them failed.  `None` checks only after a
+    /// failed read.
+    pub fn set_check_interval(&mut self, interval: Option<Duration>) {
+        self.check_interval = interval;
+    }
+
+    /// Changes the settings, which are also reapplied after every reconnect.
+    pub fn configure(&mut self, settings: Settings) -> Result<(), LinuxI2CError> {
+        self.settings = settings;
+        self.run(|bme| bme.configure(settings))
+    }
+
+    /// Changes the power mode, which is also reapplied after every reconnect.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), LinuxI2CError> {
+        self.mode = mode;
+        self.run(|bme| bme.set_mode(mode))
+    }
+
+    pub fn apply_preset(&mut self, preset: Preset) -> Result<(), LinuxI2CError> {
+        try!(self.configure(preset.settings()));
+        self.set_mode(preset.mode())
+    }
+
+    pub fn set_plausibility(&mut self, plausibility: Plausibility) {
+        self.plausibility = plausibility;
+        if let Some(ref mut bme) = *self.bme.borrow_mut() {
+            bme.set_plausibility(plausibility);
+        }
+    }
+
+    /// Number of reconnect attempts so far.
+    pub fn reconnects(&self) -> usize {
+        self.reconnects.get()
+    }
+
+    /// Returns, and forgets, the reconnect attempts since the last call.
+    pub fn take_events(&self) -> Vec<ReconnectEvent> {
+        self.events.borrow_mut().drain(..).collect()
+    }
+
+    /// Reads the current Fahrenheit temperature value from the sensor
+    pub fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
+        self.run(|bme| bme.read_temperature())
+    }
+
+    /// Reads the current barometric pressure in InHg from the sensor
+    pub fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
+        self.run(|bme| bme.read_pressure())
+    }
+
+    pub fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        self.run(|bme| bme.read_humidity())
+    }
+
+    /// Reads all three channels from a single conversion
+    pub fn read_measurement(&self) -> Result<Measurement, LinuxI2CError> {
+        self.run(|bme| bme.read_measurement())
+    }
+
+    /// Runs `op` against a healthy sensor, reconnecting first if needed
+    /// and once more if `op` fails because the sensor was lost.
+    fn run<R, G>(&self, op: G) -> Result<R, LinuxI2CError>
+        where G: Fn(&mut Bme280<T>) -> Result<R, LinuxI2CError>
+    {
+        if self.bme.borrow().is_none() || self.check_due() {
+            if let Some(reason) = self.problem() {
+                try!(self.reconnect(reason));
+            }
+        }
+        let err = match self.with_sensor(&op) {
+            Ok(result) => return Ok(result),
+            Err(err) => err,
+        };
+        let reason = if Bme280Error::find(&err).is_some() {
+            // Errors raised by the driver itself only mean the sensor was
+            // lost if its configuration no longer matches:
+            match self.problem() {
+                Some(reason) => reason,
+                None => return Err(err),
+            }
+        } else {
+            ReconnectReason::BusError(err.to_string())
+        };
+        try!(self.reconnect(reason));
+        self.with_sensor(&op)
+    }
+
+    fn check_due(&self) -> bool {
+        self.check_interval.is_some_and(|interval| self.last_check.get().elapsed() >= interval)
+    }
+
+    fn with_sensor<R, G>(&self, op: &G) -> Result<R, LinuxI2CError>
+        where G: Fn(&mut Bme280<T>) -> Result<R, LinuxI2CError>
+    {
+        let mut guard = self.bme.borrow_mut();
+        match *guard {
+            Some(ref mut bme) => op(bme),
+            None => Err(LinuxI2CError::Io(io::Error::from(io::ErrorKind::NotConnected))),
+        }
+    }
+
+    fn problem(&self) -> Option<ReconnectReason> {
+        let guard = self.bme.borrow();
+        let bme = match *guard {
+            Some(ref bme) => bme,
+            None => return Some(ReconnectReason::Disconnected),
+        };
+        self.last_check.set(Instant::now());
+        match bme.check_configuration() {
+            Ok(ConfigurationState::Intact) => None,
+            Ok(ConfigurationState::Lost) => Some(ReconnectReason::ConfigurationLost),
+            Ok(ConfigurationState::WrongChip(id)) => Some(ReconnectReason::WrongChip(id)),
+            Err(err) => Some(ReconnectReason::BusError(err.to_string())),
+        }
+    }
+
+    fn reconnect(&self, reason: ReconnectReason) -> Result<(), LinuxI2CError> {
+        // Close the old file descriptor before opening a new one:
+        *self.bme.borrow_mut() = None;
+        let result = self.connect();
+        self.reconnects.set(self.reconnects.get() + 1);
+        self.events.borrow_mut().push(ReconnectEvent {
+                                          reason,
+                                          at: SystemTime::now(),
+                                          succeeded: result.is_ok(),
+                                      });
+        result
+    }
+
+    fn connect(&self) -> Result<(), LinuxI2CError> {
+        let dev = try!((*self.open.borrow_mut())());
+        let mut bme = try!(Bme280::new_from_device(dev));
+        bme.set_plausibility(self.plausibility);
+        try!(bme.configure(self.settings));
+        try!(bme.set_mode(self.mode));
+        if let ConfigurationState::WrongChip(id) = try!(bme.check_configuration()) {
+            return Err(Bme280Error::WrongChip(id).into());
+        }
+        *self.bme.borrow_mut() = Some(bme);
+        self.last_check.set(Instant::now());
+        Ok(())
+    }
+}
+
+impl<T> Sensor for ResilientBme280<T>
+    where T: I2CDevice<Error = LinuxI2CError> + Sized
+{
+    fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
+        self.read_temperature()
+    }
+    fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
+        self.read_pressure()
+    }
+    fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        self.read_humidity()
+    }
+    fn read_measurement(&self) -> Result<Measurement, LinuxI2CError> {
+        self.read_measurement()
+    }
+}
@@ -0,0 +1,206 @@
+//! Retrying of transient bus errors, either per I2C transaction through
+//! `RetryingDevice` or per measurement through `Bme280::set_retry_policy`.
+
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CError;
+
+use super::error::Bme280Error;
+
+// Linux errno values for the failures a marginal bus typically produces:
+const EIO: i32 = 5;
+const EAGAIN: i32 = 11;
+const ETIMEDOUT: i32 = 110;
+const EREMOTEIO: i32 = 121;
+
+/// How often, and after which errors, an operation is retried.
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first.
+    pub attempts: u32,
+    /// Wait before the first retry.
+    pub backoff: Duration,
+    /// The wait is multiplied by this after every retry.
+    pub backoff_factor: u32,
+    /// Decides whether an error is worth retrying.
+    pub retryable: fn(&io::Error) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(1),
+            backoff_factor: 2,
+            retryable: is_transient,
+        }
+    }
+}
+
+/// The default retry predicate: EIO, EAGAIN, ETIMEDOUT and EREMOTEIO, plus
+/// samples rejected as implausible, which are usually a glitch on the bus.
+pub fn is_transient(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(EIO) | Some(EAGAIN) | Some(ETIMEDOUT) | Some(EREMOTEIO) => true,
+        Some(_) => false,
+        None => {
+            match err.get_ref().and_then(|inner| inner.downcast_ref::<Bme280Error>()) {
+                Some(&Bme280Error::Implausible(_)) => true,
+                _ => err.kind() == io::ErrorKind::TimedOut,
+            }
+        }
+    }
+}
+
+/// Running totals kept by a retrying device or sensor, for spotting
+/// degrading wiring.
+#[derive(Debug, Default)]
+pub struct RetryCounters {
+    retries: AtomicUsize,
+    recoveries: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl RetryCounters {
+    /// Number of attempts made after a first one failed.
+    pub fn retries(&self) -> usize {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Number of operations that succeeded only after retrying.
+    pub fn recoveries(&self) -> usize {
+        self.recoveries.load(Ordering::Relaxed)
+    }
+
+    /// Number of operations that failed for good.
+    pub fn failures(&self) -> usize {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs `op` until it succeeds, fails with an error `policy` does not
+/// consider retryable, or runs out of attempts.  Errors are returned as
+/// `LinuxI2CError::Io`, which keeps the original errno.
+pub fn retry<R, F>(policy: &RetryPolicy, counters: &RetryCounters, mut op: F) -> Result<R, LinuxI2CError>
+    where F: FnMut() -> Result<R, LinuxI2CError>
+{
+    let mut backoff = policy.backoff;
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Ok(result) => {
+                if attempt > 1 {
+                    counters.recoveries.fetch_add(1, Ordering::Relaxed);
+                }
+                return Ok(result);
+            }
+            Err(err) => {
+                let err: io::Error = err.into();
+                if attempt >= policy.attempts || !(policy.retryable)(&err) {
+                    counters.failures.fetch_add(1, Ordering::Relaxed);
+                    return Err(LinuxI2CError::Io(err));
+                }
+                counters.retries.fetch_add(1, Ordering::Relaxed);
+                thread::sleep(backoff);
+                backoff *= policy.backoff_factor;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Decorator that retries every transaction on the wrapped device.
+pub struct RetryingDevice<T: I2CDevice<Error = LinuxI2CError> + Sized> {
+    device: T,
+    policy: RetryPolicy,
+    counters: Arc<RetryCounters>,
+}
+
+impl<T: I2CDevice<Error = LinuxI2CError> + Sized> RetryingDevice<T> {
+    pub fn new(device: T, policy: RetryPolicy) -> RetryingDevice<T> {
+        RetryingDevice {
+            device,
+            policy,
+            counters: Arc::new(RetryCounters::default()),
+        }
+    }
+
+    /// Returns the counters, which stay reachable after the device has
+    /// been handed to a `Bme280`.
+    pub fn counters(&self) -> Arc<RetryCounters> {
+        self.counters.clone()
+    }
+
+    fn run<R, F>(&mut self, mut op: F) -> Result<R, LinuxI2CError>
+        where F: FnMut(&mut T) -> Result<R, LinuxI2CError>
+    {
+        let RetryingDevice { ref mut device, ref policy, ref counters } = *self;
+        retry(policy, counters, || op(device))
+    }
+}
+
+impl<T> I2CDevice for RetryingDevice<T>
+    where T: I2CDevice<Error = LinuxI2CError> + Sized
+{
+    type Error = LinuxI2CError;
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.run(|dev| dev.read(data))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.run(|dev| dev.write(data))
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_quick(bit))
+    }
+
+    fn smbus_read_byte(&mut self) -> Result<u8, Self::Error> {
+        self.run(|dev| dev.smbus_read_byte())
+    }
+
+    fn smbus_write_byte(&mut self, value: u8) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_byte(value))
+    }
+
+    fn smbus_read_byte_data(&mut self, register: u8) -> Result<u8, Self::Error> {
+        self.run(|dev| dev.smbus_read_byte_data(register))
+    }
+
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_byte_data(register, value))
+    }
+
+    fn smbus_read_word_data(&mut self, register: u8) -> Result<u16, Self::Error> {
+        self.run(|dev| dev.smbus_read_word_data(register))
+    }
+
+    fn smbus_write_word_data(&mut self, register: u8, value: u16) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_word_data(register, value))
+    }
+
+    fn smbus_process_word(&mut self, register: u8, value: u16) -> Result<u16, Self::Error> {
+        self.run(|dev| dev.smbus_process_word(register, value))
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>, Self::Error> {
+        self.run(|dev| dev.smbus_read_block_data(register))
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>, Self::Error> {
+        self.run(|dev| dev.smbus_read_i2c_block_data(register, len))
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_write_block_data(register, values))
+    }
+
+    fn smbus_process_block(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        self.run(|dev| dev.smbus_process_block(register, values))
+    }
+}
@@ -0,0 +1,176 @@
+//! Sharing one bus device between this and other drivers in the same
+//! process, e.g. when the bus also carries an RTC and an ADC.  Every
+//! driver gets a proxy that points the shared device at its own address
+//! before each transaction, so only one file descriptor is needed.
+
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+use nix::libc;
+
+use super::address::Bme280Address;
+use super::bme280::Bme280;
+use super::lock;
+
+/// Linux ioctl that sets the address an i2c-dev file descriptor talks to.
+const I2C_SLAVE: u64 = 0x0703;
+
+/// A bus device that can be pointed at any address on the bus.
+pub trait Bus: I2CDevice<Error = LinuxI2CError> {
+    fn set_address(&mut self, address: u16) -> Result<(), LinuxI2CError>;
+}
+
+impl Bus for LinuxI2CDevice {
+    fn set_address(&mut self, address: u16) -> Result<(), LinuxI2CError> {
+        // i2cdev keeps its own setter private:
+        let result = unsafe { libc::ioctl(self.as_raw_fd(), I2C_SLAVE as _, address as libc::c_ulong) };
+        if result < 0 {
+            return Err(LinuxI2CError::Io(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+struct BusState<B> {
+    bus: B,
+    /// Address the bus device currently points at, if known.
+    address: Option<u16>,
+}
+
+/// A bus device shared between drivers.  Clones refer to the same device,
+/// which is locked for every transaction, so proxies may be used from
+/// several threads.
+pub struct SharedBus<B: Bus> {
+    state: Arc<Mutex<BusState<B>>>,
+}
+
+impl<B: Bus> Clone for SharedBus<B> {
+    fn clone(&self) -> SharedBus<B> {
+        SharedBus { state: self.state.clone() }
+    }
+}
+
+impl SharedBus<LinuxI2CDevice> {
+    /// Opens the bus device at `path`, e.g. `/dev/i2c-1`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SharedBus<LinuxI2CDevice>, LinuxI2CError> {
+        // The general call address is never claimed by a kernel driver;
+        // every proxy sets its own address before use anyway:
+        Ok(SharedBus::new(try!(LinuxI2CDevice::new(path, 0))))
+    }
+}
+
+impl<B: Bus> SharedBus<B> {
+    pub fn new(bus: B) -> SharedBus<B> {
+        SharedBus {
+            state: Arc::new(Mutex::new(BusState {
+                                           bus,
+                                           address: None,
+                                       })),
+        }
+    }
+
+    /// Returns a proxy for the device at `address`, usable by any driver
+    /// that takes an `I2CDevice`.
+    pub fn device(&self, address: u16) -> BusProxy<B> {
+        BusProxy {
+            state: self.state.clone(),
+            address,
+        }
+    }
+}
+
+impl<B: Bus> Bme280<BusProxy<B>> {
+    /// Initializes the sensor at `address` on a bus shared with other drivers.
+    pub fn new_on_shared_bus(bus: &SharedBus<B>, address: Bme280Address) -> Result<Bme280<BusProxy<B>>, LinuxI2CError> {
+        Bme280::new_from_device(bus.device(address.value()))
+    }
+}
+
+/// One device's view of a shared bus.
+pub struct BusProxy<B: Bus> {
+    state: Arc<Mutex<BusState<B>>>,
+    address: u16,
+}
+
+impl<B: Bus> BusProxy<B> {
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    /// Runs `op` with the bus locked and pointed at this device.
+    fn run<R, F>(&mut self, op: F) -> Result<R, LinuxI2CError>
+        where F: FnOnce(&mut B) -> Result<R, LinuxI2CError>
+    {
+        let mut state = lock::lock_shared(&self.state);
+        if state.address != Some(self.address) {
+            // Forget the old address first in case setting the new one fails:
+            state.address = None;
+            try!(state.bus.set_address(self.address));
+            state.address = Some(self.address);
+        }
+        op(&mut state.bus)
+    }
+}
+
+impl<B: Bus> I2CDevice for BusProxy<B> {
+    type Error = LinuxI2CError;
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.run(|bus| bus.read(data))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.run(|bus| bus.write(data))
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> Result<(), Self::Error> {
+        self.run(|bus| bus.smbus_write_quick(bit))
+    }
+
+    fn smbus_read_byte(&mut self) -> Result<u8, Self::Error> {
+        self.run(|bus| bus.smbus_read_byte())
+    }
+
+    fn smbus_write_byte(&mut self, value: u8) -> Result<(), Self::Error> {
+        self.run(|bus| bus.smbus_write_byte(value))
+    }
+
+    fn smbus_read_byte_data(&mut self, register: u8) -> Result<u8, Self::Error> {
+        self.run(|bus| bus.smbus_read_byte_data(register))
+    }
+
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        self.run(|bus| bus.smbus_write_byte_data(register, value))
+    }
+
+    fn smbus_read_word_data(&mut self, register: u8) -> Result<u16, Self::Error> {
+        self.run(|bus| bus.smbus_read_word_data(register))
+    }
+
+    fn smbus_write_word_data(&mut self, register: u8, value: u16) -> Result<(), Self::Error> {
+        self.run(|bus| bus.smbus_write_word_data(register, value))
+    }
+
+    fn smbus_process_word(&mut self, register: u8, value: u16) -> Result<u16, Self::Error> {
+        self.run(|bus| bus.smbus_process_word(register, value))
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>, Self::Error> {
+        self.run(|bus| bus.smbus_read_block_data(register))
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>, Self::Error> {
+        self.run(|bus| bus.smbus_read_i2c_block_data(register, len))
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        self.run(|bus| bus.smbus_write_block_data(register, values))
+    }
+
+    fn smbus_process_block(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        self.run(|bus| bus.smbus_process_block(register, values))
+    }
+}
@@ -0,0 +1,86 @@
+//! Measurement time and output data rate, per appendix B of the datasheet.
+
+use std::time::Duration;
+
+use super::register::{Oversampling, Standby};
+
+/// How long a single conversion takes with a given set of oversampling settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeasurementTime {
+    pub typical: Duration,
+    pub max: Duration,
+}
+
+/// Computes the typical and maximum time of one conversion.  Skipped
+/// channels add nothing.
+pub fn measurement_time(osrs_t: Oversampling,
+                        osrs_p: Oversampling,
+                        osrs_h: Oversampling)
+                        -> MeasurementTime {
+    MeasurementTime {
+        typical: from_millis(measurement_millis(osrs_t, osrs_p, osrs_h, 1.0, 2.0, 0.5)),
+        max: from_millis(measurement_millis(osrs_t, osrs_p, osrs_h, 1.25, 2.3, 0.575)),
+    }
+}
+
+/// Computes the rate, in Hz, at which the sensor produces new values in
+/// normal mode with the given oversampling and standby time.
+pub fn output_data_rate(osrs_t: Oversampling,
+                        osrs_p: Oversampling,
+                        osrs_h: Oversampling,
+                        standby: Standby)
+                        -> f64 {
+    let t_measure = measurement_millis(osrs_t, osrs_p, osrs_h, 1.0, 2.0, 0.5);
+    1000.0 / (t_measure + standby_millis(standby))
+}
+
+/// Computes the longest time between the starts of two consecutive
+/// normal-mode conversions.
+pub fn conversion_period(osrs_t: Oversampling,
+                         osrs_p: Oversampling,
+                         osrs_h: Oversampling,
+                         standby: Standby)
+                         -> Duration {
+    from_millis(measurement_millis(osrs_t, osrs_p, osrs_h, 1.25, 2.3, 0.575) +
+                standby_millis(standby))
+}
+
+/// The datasheet's formula, with its typical or maximum constants: a fixed
+/// start-up time, a per-sample time, and a per-channel setup time for
+/// pressure and humidity.
+fn measurement_millis(osrs_t: Oversampling,
+                      osrs_p: Oversampling,
+                      osrs_h: Oversampling,
+                      startup: f64,
+                      per_sample: f64,
+                      setup: f64)
+                      -> f64 {
+    let mut millis = startup;
+    if osrs_t != Oversampling::Skipped {
+        millis += per_sample * osrs_t.factor() as f64;
+    }
+    if osrs_p != Oversampling::Skipped {
+        millis += per_sample * osrs_p.factor() as f64 + setup;
+    }
+    if osrs_h != Oversampling::Skipped {
+        millis += per_sample * osrs_h.factor() as f64 + setup;
+    }
+    millis
+}
+
+fn standby_millis(standby: Standby) -> f64 {
+    match standby {
+        Standby::Ms0_5 => 0.5,
+        Standby::Ms62_5 => 62.5,
+        Standby::Ms125 => 125.0,
+        Standby::Ms250 => 250.0,
+        Standby::Ms500 => 500.0,
+        Standby::Ms1000 => 1000.0,
+        Standby::Ms10 => 10.0,
+        Standby::Ms20 => 20.0,
+    }
+}
+
+fn from_millis(millis: f64) -> Duration {
+    Duration::from_micros((millis * 1000.0).ceil() as u64)
+}
@@ -0,0 +1,109 @@
+//! Typestate wrapper around `Bme280` that encodes the sensor's power mode
+//! in the type, so that e.g. reading a sensor left in sleep mode is a
+//! compile error rather than a stale value at runtime.
+
+use std::marker::PhantomData;
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CError;
+
+use super::bme280::{Bme280, Sensor};
+use super::measurement::Measurement;
+use super::register::Mode;
+
+/// Sensor is idle; no conversions take place and nothing can be read.
+pub struct Sleep;
+
+/// Every read triggers a single conversion, after which the sensor
+/// returns to sleep.
+pub struct Forced;
+
+/// Sensor converts continuously; reads return the latest conversion.
+pub struct Normal;
+
+/// Implemented by the power modes in which measurements may be read.
+pub trait Readable {}
+
+impl Readable for Forced {}
+impl Readable for Normal {}
+
+/// A `Bme280` whose power mode `M` is tracked by the type system.  Mode
+/// transitions consume the sensor and return it in its new mode.
+pub struct TypedBme280<T: I2CDevice<Error = LinuxI2CError> + Sized, M> {
+    bme: Bme280<T>,
+    _mode: PhantomData<M>,
+}
+
+impl<T: I2CDevice<Error = LinuxI2CError> + Sized> TypedBme280<T, Sleep> {
+    /// Takes ownership of a sensor and puts it to sleep.
+    pub fn new(bme: Bme280<T>) -> Result<TypedBme280<T, Sleep>, LinuxI2CError> {
+        transition(bme, Mode::Sleep)
+    }
+}
+
+impl<T: I2CDevice<Error = LinuxI2CError> + Sized, M> TypedBme280<T, M> {
+    pub fn into_sleep(self) -> Result<TypedBme280<T, Sleep>, LinuxI2CError> {
+        transition(self.bme, Mode::Sleep)
+    }
+
+    pub fn into_forced(self) -> Result<TypedBme280<T, Forced>, LinuxI2CError> {
+        transition(self.bme, Mode::Forced)
+    }
+
+    pub fn into_normal(self) -> Result<TypedBme280<T, Normal>, LinuxI2CError> {
+        transition(self.bme, Mode::Normal)
+    }
+
+    /// Releases the underlying sensor, leaving it in its current power mode.
+    pub fn into_inner(self) -> Bme280<T> {
+        self.bme
+    }
+}
+
+fn transition<T, N>(mut bme: Bme280<T>, mode: Mode) -> Result<TypedBme280<T, N>, LinuxI2CError>
+    where T: I2CDevice<Error = LinuxI2CError> + Sized
+{
+    try!(bme.set_mode(mode));
+    Ok(TypedBme280 {
+           bme,
+           _mode: PhantomData,
+       })
+}
+
+impl<T: I2CDevice<Error = LinuxI2CError> + Sized, M: Readable> TypedBme280<T, M> {
+    /// Reads the current Fahrenheit temperature value from the sensor
+    pub fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
+        self.bme.read_temperature()
+    }
+
+    /// Reads the current barometric pressure in InHg from the sensor
+    pub fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
+        self.bme.read_pressure()
+    }
+
+    pub fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        self.bme.read_humidity()
+    }
+
+    /// Reads all three channels from a single conversion
+    pub fn read_measurement(&self) -> Result<Measurement, LinuxI2CError> {
+        self.bme.read_measurement()
+    }
+}
+
+impl<T, M> Sensor for TypedBme280<T, M>
+    where T: I2CDevice<Error = LinuxI2CError> + Sized,
+          M: Readable
+{
+    fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
+        self.bme.read_temperature()
+    }
+    fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
+        self.bme.read_pressure()
+    }
+    fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        self.bme.read_humidity()
+    }
+    fn read_measurement(&self) -> Result<Measurement, LinuxI2CError> {
+        self.bme.read_measurement()
+    }
+}
@@ -0,0 +1,87 @@
+//! Periodic health check that notices when the sensor has lost its
+//! configuration, e.g. after a brown-out put it back to sleep with default
+//! registers, and restores it.
+
+use std::time::{Duration, Instant};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CError;
+
+use super::bme280::{Bme280, ConfigurationState};
+
+/// Something the watchdog noticed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogEvent {
+    /// The registers had lost their configuration, which has been reapplied.
+    ResetDetected,
+    /// The device did not identify itself as a BME280.  Nothing is written
+    /// to it.
+    WrongChip(u8),
+}
+
+pub struct Watchdog {
+    interval: Duration,
+    last_check: Option<Instant>,
+    resets_detected: usize,
+    on_event: Option<Box<dyn FnMut(WatchdogEvent)>>,
+}
+
+impl Watchdog {
+    /// Creates a watchdog that checks at most once per `interval` when polled.
+    pub fn new(interval: Duration) -> Watchdog {
+        Watchdog {
+            interval,
+            last_check: None,
+            resets_detected: 0,
+            on_event: None,
+        }
+    }
+
+    /// Registers a callback invoked with every event.
+    pub fn on_event<F>(&mut self, callback: F)
+        where F: FnMut(WatchdogEvent) + 'static
+    {
+        self.on_event = Some(Box::new(callback));
+    }
+
+    /// Number of sensor resets detected so far.
+    pub fn resets_detected(&self) -> usize {
+        self.resets_detected
+    }
+
+    /// Checks the sensor if the interval has passed since the last check.
+    /// Returns `None` when it was not yet time to check.
+    pub fn poll<T>(&mut self, bme: &Bme280<T>) -> Result<Option<ConfigurationState>, LinuxI2CError>
+        where T: I2CDevice<Error = LinuxI2CError> + Sized
+    {
+        match self.last_check {
+            Some(last) if last.elapsed() < self.interval => Ok(None),
+            _ => self.check(bme).map(Some),
+        }
+    }
+
+    /// Compares the live chip ID and control registers against the
+    /// driver's cached configuration, reapplying it if it has been lost.
+    /// Returns what the comparison found.
+    pub fn check<T>(&mut self, bme: &Bme280<T>) -> Result<ConfigurationState, LinuxI2CError>
+        where T: I2CDevice<Error = LinuxI2CError> + Sized
+    {
+        self.last_check = Some(Instant::now());
+        let state = try!(bme.check_configuration());
+        match state {
+            ConfigurationState::Intact => {}
+            ConfigurationState::Lost => {
+                try!(bme.reapply());
+                self.resets_detected += 1;
+                self.notify(WatchdogEvent::ResetDetected);
+            }
+            ConfigurationState::WrongChip(id) => self.notify(WatchdogEvent::WrongChip(id)),
+        }
+        Ok(state)
+    }
+
+    fn notify(&mut self, event: WatchdogEvent) {
+        if let Some(ref mut callback) = self.on_event {
+            callback(event);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CError;
+use bme280::register::Register;
+use bme280::shared_bus::Bus;
+
+/// Fake sensor backed by a 256-byte register file.  Word reads are little
+/// endian, matching the sensor, and every byte write is recorded.  It is
+/// preloaded with the same calibration and measurement data as the
+/// `FakeDevice` in unit_test.rs, so the same known values come back.
+/// Clones share the same state, so a test can keep one to inspect the
+/// device after handing the other to a `Bme280`.
+#[derive(Clone)]
+pub struct RegisterMapDevice {
+    state: Rc<RefCell<RegisterMap>>,
+}
+
+pub struct RegisterMap {
+    pub registers: [u8; 256],
+    pub writes: Vec<(u8, u8)>,
+    /// Number of upcoming transactions that fail with EIO.
+    pub failures_pending: usize,
+    /// Register whose upcoming writes are silently lost, and how many.
+    pub lost_writes: Option<(u8, usize)>,
+}
+
+impl RegisterMapDevice {
+    pub fn new() -> RegisterMapDevice {
+        let dev = RegisterMapDevice {
+            state: Rc::new(RefCell::new(RegisterMap {
+                                            registers: [0; 256],
+                                            writes: Vec::new(),
+                                            failures_pending: 0,
+                                            lost_writes: None,
+                                        })),
+        };
+        let words = [(Register::T1, 28960), (Register::T2, 26619), (Register::T3, 50),
+                     (Register::P1, 34988), (Register::P2, 54823), (Register::P3, 3024),
+                     (Register::P4, 5831), (Register::P5, 96), (Register::P6, 65529),
+                     (Register::P7, 9900), (Register::P8, 55306), (Register::P9, 4285),
+                     (Register::H2, 355)];
+        for &(register, value) in words.iter() {
+            dev.set_word(register, value);
+        }
+        let bytes = [(Register::H1, 75), (Register::H3, 0), (Register::H4, 21),
+                     (Register::H5, 0), (Register::H6, 0), (Register::H7, 32),
+                     (Register::ChipId, 0x60),
+                     (Register::TemperatureData, 129), (Register::TemperatureData1, 142),
+                     (Register::TemperatureData2, 0),
+                     (Register::PressureData, 92), (Register::PressureData1, 215),
+                     (Register::PressureData2, 112),
+                     (Register::HumidityData, 111), (Register::HumidityData1, 159)];
+        for &(register, value) in bytes.iter() {
+            dev.set(register, value);
+        }
+        dev
+    }
+
+    pub fn set_word(&self, register: Register, value: u16) {
+        self.set(register, value as u8);
+        self.state.borrow_mut().registers[register as usize + 1] = (value >> 8) as u8;
+    }
+
+    pub fn set(&self, register: Register, value: u8) {
+        self.state.borrow_mut().registers[register as usize] = value;
+    }
+
+    pub fn get(&self, register: Register) -> u8 {
+        self.state.borrow().registers[register as usize]
+    }
+
+    /// Makes the next `count` transactions fail with EIO.
+    pub fn fail_next(&self, count: usize) {
+        self.state.borrow_mut().failures_pending = count;
+    }
+
+    /// Makes the next `count` writes to `register` succeed without effect.
+    pub fn lose_writes(&self, register: Register, count: usize) {
+        self.state.borrow_mut().lost_writes = Some((register as u8, count));
+    }
+
+    fn check_failure(&self) -> Result<(), LinuxI2CError> {
+        let mut state = self.state.borrow_mut();
+        if state.failures_pending > 0 {
+            state.failures_pending -= 1;
+            return Err(LinuxI2CError::Io(io::Error::from_raw_os_error(5)));
+        }
+        Ok(())
+    }
+
+    /// Every (register, value) byte write seen so far, oldest first.
+    pub fn writes(&self) -> Vec<(u8, u8)> {
+        self.state.borrow().writes.clone()
+    }
+}
+
+impl I2CDevice for RegisterMapDevice {
+    type Error = LinuxI2CError;
+
+    fn read(&mut self, _data: &mut [u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn write(&mut self, _data: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn smbus_write_quick(&mut self, _bit: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>, Self::Error> {
+        self.smbus_read_i2c_block_data(register, 32)
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>, Self::Error> {
+        let start = register as usize;
+        let end = ::std::cmp::min(start + len as usize, 256);
+        Ok(self.state.borrow().registers[start..end].to_vec())
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        for (i, value) in values.iter().enumerate() {
+            try!(self.smbus_write_byte_data(register + i as u8, *value));
+        }
+        Ok(())
+    }
+
+    fn smbus_process_block(&mut self, _register: u8, _values: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn smbus_read_word_data(&mut self, register: u8) -> Result<u16, Self::Error> {
+        try!(self.check_failure());
+        let state = self.state.borrow();
+        let lsb = state.registers[register as usize] as u16;
+        let msb = state.registers[register as usize + 1] as u16;
+        Ok(msb << 8 | lsb)
+    }
+
+    fn smbus_read_byte_data(&mut self, register: u8) -> Result<u8, Self::Error> {
+        try!(self.check_failure());
+        Ok(self.state.borrow().registers[register as usize])
+    }
+
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        try!(self.check_failure());
+        let mut state = self.state.borrow_mut();
+        state.writes.push((register, value));
+        if let Some((lost, count)) = state.lost_writes {
+            if lost == register && count > 0 {
+                state.lost_writes = Some((lost, count - 1));
+                return Ok(());
+            }
+        }
+        state.registers[register as usize] = value;
+        Ok(())
+    }
+}
+
+/// Fake multiplexer control device.  It records the channel mask of every
+/// selection into a log it shares with its clones.
+#[derive(Clone)]
+pub struct MuxControlDevice {
+    pub selections: Rc<RefCell<Vec<u8>>>,
+}
+
+impl MuxControlDevice {
+    pub fn new() -> MuxControlDevice {
+        MuxControlDevice { selections: Rc::new(RefCell::new(Vec::new())) }
+    }
+}
+
+impl I2CDevice for MuxControlDevice {
+    type Error = LinuxI2CError;
+
+    fn read(&mut self, _data: &mut [u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.selections.borrow_mut().extend_from_slice(data);
+        Ok(())
+    }
+
+    fn smbus_write_quick(&mut self, _bit: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn smbus_read_block_data(&mut self, _register: u8) -> Result<Vec<u8>, Self::Error> {
+        Ok(Vec::new())
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, _register: u8, _len: u8) -> Result<Vec<u8>, Self::Error> {
+        Ok(Vec::new())
+    }
+
+    fn smbus_write_block_data(&mut self, _register: u8, _values: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn smbus_process_block(&mut self, _register: u8, _values: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Fake bus carrying a `RegisterMapDevice` at each of several addresses.
+/// Transactions go to whichever device the bus was last pointed at, and
+/// every change of address is recorded.
+pub struct FakeBus {
+    pub devices: Vec<(u16, RegisterMapDevice)>,
+    pub address: Option<u16>,
+    pub address_changes: Rc<RefCell<Vec<u16>>>,
+}
+
+impl FakeBus {
+    pub fn new(devices: Vec<(u16, RegisterMapDevice)>) -> FakeBus {
+        FakeBus {
+            devices,
+            address: None,
+            address_changes: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    fn current(&mut self) -> Result<&mut RegisterMapDevice, LinuxI2CError> {
+        let address = self.address;
+        self.devices
+            .iter_mut()
+            .find(|&&mut (device_address, _)| Some(device_address) == address)
+            .map(|&mut (_, ref mut device)| device)
+            // ENXIO, as for an address nothing acknowledges:
+            .ok_or_else(|| LinuxI2CError::Io(io::Error::from_raw_os_error(6)))
+    }
+}
+
+impl Bus for FakeBus {
+    fn set_address(&mut self, address: u16) -> Result<(), LinuxI2CError> {
+        self.address = Some(address);
+        self.address_changes.borrow_mut().push(address);
+        Ok(())
+    }
+}
+
+impl I2CDevice for FakeBus {
+    type Error = LinuxI2CError;
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error> {
+        try!(self.current()).read(data)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        try!(self.current()).write(data)
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> Result<(), Self::Error> {
+        try!(self.current()).smbus_write_quick(bit)
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>, Self::Error> {
+        try!(self.current()).smbus_read_block_data(register)
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>, Self::Error> {
+        try!(self.current()).smbus_read_i2c_block_data(register, len)
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        try!(self.current()).smbus_write_block_data(register, values)
+    }
+
+    fn smbus_process_block(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        try!(self.current()).smbus_process_block(register, values)
+    }
+
+    fn smbus_read_word_data(&mut self, register: u8) -> Result<u16, Self::Error> {
+        try!(self.current()).smbus_read_word_data(register)
+    }
+
+    fn smbus_read_byte_data(&mut self, register: u8) -> Result<u8, Self::Error> {
+        try!(self.current()).smbus_read_byte_data(register)
+    }
+
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        try!(self.current()).smbus_write_byte_data(register, value)
+    }
+}
@@ -2,10 +2,41 @@ extern crate nix;
 extern crate i2cdev;
 extern crate bme280;
 
+mod common;
+
+use std::convert::TryFrom;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
 use i2cdev::core::I2CDevice;
 use i2cdev::linux::LinuxI2CError;
-use bme280::bme280::Bme280;
-use bme280::register::Register;
+use bme280::address::Bme280Address;
+use bme280::bus::find_bus_by_name_in;
+use bme280::bme280::{Bme280, ConfigurationState, Sensor, Settings};
+use bme280::differential::DifferentialPressure;
+use bme280::discovery::{discover_with, Discovered, Variant};
+use bme280::driver::{Binding, DriverControl};
+use bme280::error::Bme280Error;
+use bme280::accuracy::accuracy;
+use bme280::measurement::{Channel, Measurement, IN_HG_PER_PASCAL};
+use bme280::register::{Config, CtrlHum, CtrlMeas, Filter, Mode, Oversampling, Register, Standby};
+use bme280::iio::IioBme280;
+use bme280::lock::BusLock;
+use bme280::mux::Tca9548a;
+use bme280::plausibility::Plausibility;
+use bme280::preset::Preset;
+use bme280::resilient::{ReconnectReason, ResilientBme280};
+use bme280::retry::{RetryPolicy, RetryingDevice};
+use bme280::shared_bus::SharedBus;
+use bme280::timing::{measurement_time, output_data_rate};
+use bme280::typestate::TypedBme280;
+use bme280::fusion::{combine, FusionMethod, SensorFusion};
+use bme280::group::SensorGroup;
+use bme280::health::Health;
+use bme280::watchdog::{Watchdog, WatchdogEvent};
+use common::{FakeBus, MuxControlDevice, RegisterMapDevice};
 
 struct FakeDevice {}
 
@@ -125,3 +156,823 @@ fn set_of_known_calibration_values_should_yield_known_humidity() {
     println!("Humidity is {}%.", h);
     assert!((h - 38.68).abs() < 0.01);
 }
+
+#[test]
+fn typed_sensor_in_forced_mode_should_yield_known_temperature() {
+    let bme = Bme280::new_from_device(FakeDevice {}).unwrap();
+    let forced = TypedBme280::new(bme).unwrap().into_forced().unwrap();
+
+    let t = forced.read_temperature().unwrap();
+    assert!((t - 70.44).abs() < 0.01);
+}
+
+#[test]
+fn typed_sensor_transitions_should_be_reflected_in_driver_mode() {
+    let bme = Bme280::new_from_device(FakeDevice {}).unwrap();
+
+    let sleeping = TypedBme280::new(bme).unwrap();
+    let normal = sleeping.into_normal().unwrap();
+    assert_eq!(normal.into_inner().mode(), Mode::Normal);
+}
+
+#[test]
+fn control_bitfields_should_round_trip_through_raw_bytes() {
+    let meas = CtrlMeas::from(0b0101_0111);
+    assert_eq!(meas,
+               CtrlMeas {
+                   osrs_t: Oversampling::X2,
+                   osrs_p: Oversampling::X16,
+                   mode: Mode::Normal,
+               });
+    assert_eq!(u8::from(meas), 0b0101_0111);
+
+    let config = Config::from(0b1011_0001);
+    assert_eq!(config.t_sb, Standby::Ms1000);
+    assert_eq!(config.filter, Filter::X16);
+    assert!(config.spi3w_en);
+    assert_eq!(u8::from(config), 0b1011_0001);
+}
+
+#[test]
+fn register_should_convert_from_known_addresses_only() {
+    assert_eq!(Register::try_from(0xF4), Ok(Register::Control));
+    assert_eq!(Register::try_from(0xF3), Ok(Register::Status));
+    assert_eq!(Register::try_from(0x00), Err(0x00));
+}
+
+#[test]
+fn modify_bitfield_should_preserve_reserved_bits() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+    dev.set(Register::Config, 0b0000_0010);
+
+    bme.modify_bitfield(|c: Config| Config { filter: Filter::X4, ..c }).unwrap();
+
+    assert_eq!(dev.get(Register::Config), 0b0000_1010);
+    assert_eq!(bme.read_bitfield::<Config>().unwrap().filter, Filter::X4);
+}
+
+#[test]
+fn write_register_should_keep_cached_settings_in_sync() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+
+    bme.write_register(Register::Control, 0b0100_1011).unwrap();
+
+    assert_eq!(dev.get(Register::Control), 0b0100_1011);
+    assert_eq!(bme.mode(), Mode::Normal);
+    assert_eq!(bme.settings().osrs_t, Oversampling::X2);
+    assert_eq!(bme.settings().osrs_p, Oversampling::X2);
+}
+
+#[test]
+fn write_register_should_refuse_read_only_registers() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+
+    assert!(bme.write_register(Register::T1, 0).is_err());
+    assert_eq!(dev.get(Register::T1), 28960u16 as u8);
+}
+
+#[test]
+fn read_registers_should_return_consecutive_bytes() {
+    let bme = Bme280::new_from_device(RegisterMapDevice::new()).unwrap();
+
+    let data = bme.read_registers(Register::PressureData, 3).unwrap();
+    assert_eq!(data, vec![92, 215, 112]);
+    assert_eq!(bme.read_register(Register::ChipId).unwrap(), 0x60);
+}
+
+#[test]
+fn measurement_should_yield_known_values_from_one_conversion() {
+    let bme = Bme280::new_from_device(RegisterMapDevice::new()).unwrap();
+
+    let m = bme.read_measurement().unwrap();
+    assert!((m.temperature.unwrap() - 70.44).abs() < 0.01);
+    assert!((m.pressure.unwrap() - 30.14).abs() < 0.01);
+    assert!((m.humidity.unwrap() - 38.68).abs() < 0.01);
+}
+
+#[test]
+fn skipped_humidity_should_be_reported_as_disabled() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+    bme.configure(Settings { osrs_h: Oversampling::Skipped, ..Settings::default() }).unwrap();
+    dev.set(Register::HumidityData, 0x80);
+    dev.set(Register::HumidityData1, 0x00);
+
+    assert_eq!(dev.get(Register::ControlHum), 0);
+    let m = bme.read_measurement().unwrap();
+    assert!(m.temperature.is_some());
+    assert_eq!(m.humidity, None);
+
+    let err = bme.read_humidity().unwrap_err();
+    assert_eq!(Bme280Error::find(&err),
+               Some(&Bme280Error::ChannelSkipped(Channel::Humidity)));
+}
+
+#[test]
+fn measurement_time_should_follow_datasheet_formula() {
+    let t = measurement_time(Oversampling::X1, Oversampling::X1, Oversampling::X1);
+    assert_eq!(t.typical, Duration::from_micros(8000));
+    assert_eq!(t.max, Duration::from_micros(9300));
+
+    let skipped = measurement_time(Oversampling::X1, Oversampling::Skipped, Oversampling::Skipped);
+    assert_eq!(skipped.typical, Duration::from_micros(3000));
+}
+
+#[test]
+fn output_data_rate_should_match_indoor_navigation_example() {
+    let odr = output_data_rate(Oversampling::X2, Oversampling::X16, Oversampling::X1, Standby::Ms0_5);
+    assert!((odr - 24.69).abs() < 0.01);
+}
+
+#[test]
+fn applying_a_preset_should_write_its_settings_and_mode() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+
+    bme.apply_preset(Preset::IndoorNavigation).unwrap();
+
+    assert_eq!(dev.get(Register::ControlHum), 0b001);
+    assert_eq!(dev.get(Register::Config), 0b0001_0000);
+    assert_eq!(dev.get(Register::Control), 0b0101_0111);
+    assert_eq!(bme.mode(), Mode::Normal);
+    assert_eq!(bme.settings(), Preset::IndoorNavigation.settings());
+}
+
+#[test]
+fn gaming_preset_should_report_datasheet_data_rate() {
+    assert!((Preset::Gaming.data_rate() - 83.33).abs() < 0.01);
+    assert_eq!(Preset::Gaming.humidity_noise(), None);
+}
+
+#[test]
+fn precision_should_follow_oversampling_and_filter() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev).unwrap();
+    bme.apply_preset(Preset::Gaming).unwrap();
+
+    let m = bme.read_measurement().unwrap();
+    let precision = m.precision.unwrap();
+    let pressure = precision.pressure.unwrap();
+    assert!((pressure.noise / IN_HG_PER_PASCAL - 0.3).abs() < 1e-9);
+    assert!((pressure.resolution / IN_HG_PER_PASCAL - 0.16).abs() < 1e-9);
+    assert_eq!(precision.humidity, None);
+}
+
+#[test]
+fn accuracy_should_widen_outside_specified_range() {
+    let m = Measurement {
+        temperature: Some(70.0),
+        pressure: Some(30.0),
+        humidity: Some(90.0),
+        precision: None,
+        accuracy: None,
+        implausible: Vec::new(),
+    };
+
+    let a = accuracy(&m);
+    let t = a.temperature.unwrap();
+    assert!(t.in_spec);
+    assert!((t.absolute.unwrap() - 1.8).abs() < 1e-9);
+    let h = a.humidity.unwrap();
+    assert!(!h.in_spec);
+    assert_eq!(h.absolute, None);
+}
+
+#[test]
+fn accuracy_should_use_the_datasheet_extended_range_bounds() {
+    // -10 °C, i.e. 14 °F, is covered by the -20..0 °C rows:
+    let cold = Measurement {
+        temperature: Some(14.0),
+        pressure: Some(30.0),
+        humidity: Some(50.0),
+        precision: None,
+        accuracy: None,
+        implausible: Vec::new(),
+    };
+    let a = accuracy(&cold);
+    let t = a.temperature.unwrap();
+    assert!(!t.in_spec);
+    assert!((t.absolute.unwrap() - 1.25 * 1.8).abs() < 1e-9);
+    let p = a.pressure.unwrap();
+    assert!(!p.in_spec);
+    assert!((p.absolute.unwrap() / IN_HG_PER_PASCAL - 170.0).abs() < 1e-6);
+
+    // -50 °C is beyond every range the datasheet covers:
+    let colder = Measurement { temperature: Some(-58.0), ..cold };
+    let a = accuracy(&colder);
+    assert_eq!(a.temperature.unwrap().absolute, None);
+    assert_eq!(a.pressure.unwrap().absolute, None);
+}
+
+#[test]
+fn all_ones_reads_should_be_rejected_as_implausible() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+    bme.set_plausibility(Plausibility::Reject);
+    dev.set(Register::PressureData, 0xFF);
+    dev.set(Register::PressureData1, 0xFF);
+    dev.set(Register::PressureData2, 0xFF);
+
+    let err = bme.read_pressure().unwrap_err();
+    assert_eq!(Bme280Error::find(&err),
+               Some(&Bme280Error::Implausible(Channel::Pressure)));
+    assert!(bme.read_temperature().is_ok());
+}
+
+#[test]
+fn implausible_channels_should_be_flagged_when_requested() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+    bme.set_plausibility(Plausibility::Flag);
+    dev.set(Register::HumidityData, 0xFF);
+    dev.set(Register::HumidityData1, 0xFF);
+
+    let m = bme.read_measurement().unwrap();
+    assert_eq!(m.implausible, vec![Channel::Humidity]);
+}
+
+#[test]
+fn blank_calibration_should_fail_construction() {
+    let dev = RegisterMapDevice::new();
+    dev.set_word(Register::T1, 0);
+
+    match Bme280::new_from_device(dev) {
+        Ok(_) => panic!("construction should have failed"),
+        Err(err) => {
+            assert_eq!(Bme280Error::find(&err),
+                       Some(&Bme280Error::InvalidCalibration("t1 is blank")))
+        }
+    }
+}
+
+#[test]
+fn out_of_range_calibration_should_fail_construction() {
+    let dev = RegisterMapDevice::new();
+    for reg in &[Register::P2, Register::P3, Register::P4, Register::P5,
+                 Register::P6, Register::P7, Register::P8, Register::P9] {
+        dev.set_word(*reg, 0xFFFF);
+    }
+
+    match Bme280::new_from_device(dev) {
+        Ok(_) => panic!("construction should have failed"),
+        Err(err) => {
+            assert_eq!(Bme280Error::find(&err),
+                       Some(&Bme280Error::InvalidCalibration("p2 is out of range")))
+        }
+    }
+}
+
+#[test]
+fn calibration_fingerprint_should_identify_the_part() {
+    let a = Bme280::new_from_device(RegisterMapDevice::new()).unwrap();
+    let b = Bme280::new_from_device(RegisterMapDevice::new()).unwrap();
+    let other = RegisterMapDevice::new();
+    other.set_word(Register::P9, 4286);
+    let c = Bme280::new_from_device(other).unwrap();
+
+    assert_eq!(a.calibration_fingerprint(), b.calibration_fingerprint());
+    assert!(a.calibration_fingerprint() != c.calibration_fingerprint());
+}
+
+#[test]
+fn retrying_device_should_recover_from_transient_errors() {
+    let dev = RegisterMapDevice::new();
+    let retrying = RetryingDevice::new(dev.clone(), RetryPolicy::default());
+    let counters = retrying.counters();
+    let bme = Bme280::new_from_device(retrying).unwrap();
+
+    dev.fail_next(2);
+    let t = bme.read_temperature().unwrap();
+    assert!((t - 70.44).abs() < 0.01);
+    assert_eq!(counters.retries(), 2);
+    assert_eq!(counters.recoveries(), 1);
+    assert_eq!(counters.failures(), 0);
+}
+
+#[test]
+fn measurement_retries_should_give_up_after_the_policy_attempts() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+    bme.set_retry_policy(Some(RetryPolicy { attempts: 2, ..RetryPolicy::default() }));
+
+    dev.fail_next(2);
+    let err = bme.read_measurement().unwrap_err();
+    match err {
+        LinuxI2CError::Io(ref e) => assert_eq!(e.raw_os_error(), Some(5)),
+        _ => panic!("expected an I/O error"),
+    }
+    assert_eq!(bme.retry_counters().retries(), 1);
+    assert_eq!(bme.retry_counters().failures(), 1);
+    assert!(bme.read_measurement().is_ok());
+}
+
+#[test]
+fn resilient_sensor_should_reapply_settings_after_a_reset() {
+    let dev = RegisterMapDevice::new();
+    let opened = dev.clone();
+    let mut bme = ResilientBme280::new(move || Ok(opened.clone())).unwrap();
+    bme.set_check_interval(Some(Duration::from_secs(0)));
+    bme.configure(Settings { filter: Filter::X4, ..Settings::default() }).unwrap();
+
+    dev.set(Register::ControlHum, 0);
+    dev.set(Register::Control, 0);
+    dev.set(Register::Config, 0);
+    let t = bme.read_temperature().unwrap();
+
+    assert!((t - 70.44).abs() < 0.01);
+    let events = bme.take_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].reason, ReconnectReason::ConfigurationLost);
+    assert!(events[0].succeeded);
+    assert_eq!(Config::from(dev.get(Register::Config)).filter, Filter::X4);
+}
+
+#[test]
+fn resilient_sensor_should_keep_the_driver_power_mode() {
+    let dev = RegisterMapDevice::new();
+    let opened = dev.clone();
+    let bme = ResilientBme280::new(move || Ok(opened.clone())).unwrap();
+    assert_eq!(CtrlMeas::from(dev.get(Register::Control)).mode, Mode::Normal);
+
+    // Until the check interval runs out, successful reads are not
+    // preceded by a configuration check:
+    dev.set(Register::Config, 0b1110_0000);
+    assert!(bme.read_measurement().is_ok());
+    assert!(bme.take_events().is_empty());
+}
+
+#[test]
+fn resilient_sensor_should_write_presets_to_the_sensor() {
+    let dev = RegisterMapDevice::new();
+    let opened = dev.clone();
+    let mut bme = ResilientBme280::new(move || Ok(opened.clone())).unwrap();
+    bme.apply_preset(Preset::IndoorNavigation).unwrap();
+
+    let meas = CtrlMeas::from(dev.get(Register::Control));
+    assert_eq!((meas.osrs_t, meas.osrs_p, meas.mode),
+               (Oversampling::X2, Oversampling::X16, Mode::Normal));
+    assert_eq!(dev.get(Register::ControlHum) & 0b111, Oversampling::X1 as u8);
+    let config = Config::from(dev.get(Register::Config));
+    assert_eq!((config.filter, config.t_sb), (Filter::X16, Standby::Ms0_5));
+    assert!(bme.take_events().is_empty());
+}
+
+#[test]
+fn resilient_sensor_should_reconnect_after_a_bus_error() {
+    let dev = RegisterMapDevice::new();
+    let opened = dev.clone();
+    let bme = ResilientBme280::new(move || Ok(opened.clone())).unwrap();
+
+    dev.fail_next(1);
+    assert!(bme.read_measurement().is_ok());
+    match bme.take_events()[0].reason {
+        ReconnectReason::BusError(_) => {}
+        ref other => panic!("unexpected reason {:?}", other),
+    }
+}
+
+#[test]
+fn constructor_should_start_the_sensor_in_normal_mode() {
+    let dev = RegisterMapDevice::new();
+    let bme = Bme280::new_from_device(dev.clone()).unwrap();
+
+    // Control ends up as 0x3F used to: 1x temperature, 16x pressure
+    // oversampling and normal mode, with 1x humidity latched before it.
+    let writes = dev.writes();
+    let registers: Vec<u8> = writes.iter().map(|&(register, _)| register).collect();
+    assert_eq!(registers,
+               vec![Register::ControlHum as u8, Register::Control as u8, Register::Config as u8,
+                    Register::ControlHum as u8, Register::Control as u8]);
+    assert_eq!(CtrlHum::from(dev.get(Register::ControlHum)).osrs_h, Oversampling::X1);
+    let meas = CtrlMeas::from(dev.get(Register::Control));
+    assert_eq!((meas.osrs_t, meas.osrs_p, meas.mode),
+               (Oversampling::X1, Oversampling::X16, Mode::Normal));
+    assert_eq!(bme.mode(), Mode::Normal);
+}
+
+#[test]
+fn constructor_should_configure_a_sensor_fresh_from_power_on_reset() {
+    let dev = RegisterMapDevice::new();
+    for &register in &[Register::ControlHum, Register::Control, Register::Config] {
+        assert_eq!(dev.get(register), 0);
+    }
+    let bme = Bme280::new_from_device(dev.clone()).unwrap();
+
+    assert_eq!(bme.check_configuration().unwrap(), ConfigurationState::Intact);
+    assert!(bme.read_humidity().is_ok());
+    assert!(bme.read_measurement().unwrap().humidity.is_some());
+}
+
+#[test]
+fn verified_writes_should_retry_until_the_value_sticks() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+    bme.set_write_verification(Some(1));
+
+    dev.lose_writes(Register::Config, 1);
+    bme.configure(Settings { filter: Filter::X8, ..Settings::default() }).unwrap();
+    assert_eq!(Config::from(dev.get(Register::Config)).filter, Filter::X8);
+}
+
+#[test]
+fn verified_writes_should_fail_when_retries_run_out() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+    bme.set_write_verification(Some(0));
+
+    dev.lose_writes(Register::ControlHum, 1);
+    let err = bme.configure(Settings { osrs_h: Oversampling::X4, ..Settings::default() })
+        .unwrap_err();
+    assert_eq!(Bme280Error::find(&err),
+               Some(&Bme280Error::WriteMismatch {
+                   register: Register::ControlHum,
+                   wrote: 0b011,
+                   read: 0b001,
+               }));
+}
+
+#[test]
+fn watchdog_should_reapply_configuration_after_a_reset() {
+    let dev = RegisterMapDevice::new();
+    let bme = Bme280::new_from_device(dev.clone()).unwrap();
+    let mut watchdog = Watchdog::new(Duration::from_secs(3600));
+    let resets = Rc::new(Cell::new(0));
+    let seen = resets.clone();
+    watchdog.on_event(move |event| if event == WatchdogEvent::ResetDetected {
+                          seen.set(seen.get() + 1)
+                      });
+
+    assert_eq!(watchdog.poll(&bme).unwrap(), Some(ConfigurationState::Intact));
+    dev.set(Register::Control, 0);
+    assert_eq!(watchdog.poll(&bme).unwrap(), None);
+    assert_eq!(watchdog.check(&bme).unwrap(), ConfigurationState::Lost);
+
+    assert_eq!(CtrlMeas::from(dev.get(Register::Control)).mode, Mode::Normal);
+    assert_eq!(watchdog.resets_detected(), 1);
+    assert_eq!(resets.get(), 1);
+}
+
+#[test]
+fn one_read_cycle_should_count_each_channel_once() {
+    let mut bme = Bme280::new_from_device(RegisterMapDevice::new()).unwrap();
+    bme.set_flatline_threshold(3);
+
+    bme.set_mode(Mode::Forced).unwrap();
+    bme.read_temperature().unwrap();
+    bme.read_pressure().unwrap();
+    bme.read_humidity().unwrap();
+    assert_eq!(bme.health(), Health::Healthy);
+
+    // Re-reads of one normal-mode conversion are not repeats either:
+    bme.set_flatline_threshold(3);
+    bme.configure(Settings { standby: Standby::Ms1000, ..Settings::default() }).unwrap();
+    bme.set_mode(Mode::Normal).unwrap();
+    bme.read_temperature().unwrap();
+    bme.read_pressure().unwrap();
+    bme.read_humidity().unwrap();
+    bme.read_measurement().unwrap();
+    assert_eq!(bme.health(), Health::Healthy);
+}
+
+#[test]
+fn should_flag_channels_whose_raw_values_flatline() {
+    let dev = RegisterMapDevice::new();
+    let mut bme = Bme280::new_from_device(dev.clone()).unwrap();
+    bme.set_mode(Mode::Forced).unwrap();
+    bme.set_flatline_threshold(3);
+
+    bme.read_measurement().unwrap();
+    bme.read_measurement().unwrap();
+    assert_eq!(bme.health(), Health::Healthy);
+    bme.read_measurement().unwrap();
+    assert_eq!(bme.health(),
+               Health::Stuck(vec![Channel::Temperature, Channel::Pressure, Channel::Humidity]));
+
+    dev.set(Register::TemperatureData2, 0x10);
+    bme.read_temperature().unwrap();
+    assert_eq!(bme.health(), Health::Stuck(vec![Channel::Pressure, Channel::Humidity]));
+}
+
+#[test]
+fn should_only_accept_bme280_addresses() {
+    assert_eq!(Bme280Address::try_from(0x76), Ok(Bme280Address::Primary));
+    assert_eq!(Bme280Address::try_from(0x77), Ok(Bme280Address::Secondary));
+    assert_eq!(Bme280Address::try_from(0x67), Err(0x67));
+    assert_eq!(Bme280Address::from_sdo(true).value(), 0x77);
+}
+
+#[test]
+fn should_resolve_bus_by_adapter_name() {
+    let root = std::env::temp_dir().join(format!("bme280-sysfs-{}", std::process::id()));
+    for &(bus, name) in [(1, "bcm2835 (i2c@7e804000)"), (11, "sensors\n")].iter() {
+        let dir = root.join(format!("i2c-{}", bus));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("name"), name).unwrap();
+    }
+    fs::create_dir_all(root.join("1-0077")).unwrap();
+
+    assert_eq!(find_bus_by_name_in(&root, "sensors").unwrap(),
+               std::path::PathBuf::from("/dev/i2c-11"));
+    let err = find_bus_by_name_in(&root, "missing").unwrap_err();
+    assert_eq!(Bme280Error::find(&err),
+               Some(&Bme280Error::UnknownBus("missing".to_string())));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn should_discover_sensors_on_given_buses() {
+    let bmp = RegisterMapDevice::new();
+    bmp.set(Register::ChipId, 0x58);
+    let foreign = RegisterMapDevice::new();
+    foreign.set(Register::ChipId, 0x12);
+    let buses = ["/dev/i2c-0", "/dev/i2c-1"];
+
+    let found = discover_with(&buses, |bus, address| match (bus.to_str().unwrap(), address) {
+        ("/dev/i2c-0", 0x76) => Ok(foreign.clone()),
+        ("/dev/i2c-1", 0x76) => Ok(bmp.clone()),
+        ("/dev/i2c-1", 0x77) => Ok(RegisterMapDevice::new()),
+        _ => Err(LinuxI2CError::Io(std::io::Error::from_raw_os_error(6))),
+    });
+
+    assert_eq!(found,
+               vec![Discovered {
+                        bus: "/dev/i2c-1".into(),
+                        address: Bme280Address::Primary,
+                        variant: Variant::Bmp280,
+                    },
+                    Discovered {
+                        bus: "/dev/i2c-1".into(),
+                        address: Bme280Address::Secondary,
+                        variant: Variant::Bme280,
+                    }]);
+}
+
+#[test]
+fn mux_channel_should_be_selected_before_every_transaction() {
+    let control = MuxControlDevice::new();
+    let mux = Tca9548a::new(control.clone());
+    let near = Bme280::new_from_device(mux.channel(2, RegisterMapDevice::new()).unwrap()).unwrap();
+    let far_dev = RegisterMapDevice::new();
+    far_dev.set(Register::TemperatureData2, 0x10);
+    let far = Bme280::new_from_device(mux.channel(7, far_dev).unwrap()).unwrap();
+
+    control.selections.borrow_mut().clear();
+    let near_temperature = near.read_temperature().unwrap();
+    assert!(control.selections.borrow().iter().all(|&mask| mask == 0b0000_0100));
+    let far_temperature = far.read_temperature().unwrap();
+    assert_eq!(*control.selections.borrow().last().unwrap(), 0b1000_0000);
+    assert!(far_temperature != near_temperature);
+
+    let err = mux.channel(8, RegisterMapDevice::new()).err().unwrap();
+    assert_eq!(Bme280Error::find(&err), Some(&Bme280Error::InvalidMuxChannel(8)));
+}
+
+#[test]
+fn should_share_a_bus_with_other_drivers() {
+    let sensor = RegisterMapDevice::new();
+    let rtc = RegisterMapDevice::new();
+    let fake_bus = FakeBus::new(vec![(0x76, sensor.clone()), (0x68, rtc.clone())]);
+    let address_changes = fake_bus.address_changes.clone();
+    let bus = SharedBus::new(fake_bus);
+
+    let bme = Bme280::new_on_shared_bus(&bus, Bme280Address::Primary).unwrap();
+    let mut other_driver = bus.device(0x68);
+    other_driver.smbus_write_byte_data(0x0E, 0x1C).unwrap();
+    bme.read_temperature().unwrap();
+
+    assert_eq!(*address_changes.borrow(), vec![0x76, 0x68, 0x76]);
+    assert_eq!(rtc.writes(), vec![(0x0E, 0x1C)]);
+    assert!(!sensor.writes().contains(&(0x0E, 0x1C)));
+}
+
+#[test]
+fn measurements_should_wait_for_the_bus_lock() {
+    let dir = std::env::temp_dir().join(format!("bme280-lock-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let lock = BusLock::for_device_in(&dir, "/dev/i2c-1", Bme280Address::Secondary);
+    assert_eq!(lock.path(), dir.join("bme280-i2c-1-77.lock").as_path());
+    let mut bme = Bme280::new_from_device(RegisterMapDevice::new()).unwrap();
+    bme.set_bus_lock(Some(lock.clone()));
+
+    let held = lock.acquire().unwrap();
+    let start = Instant::now();
+    let holder = thread::spawn(move || {
+                                   thread::sleep(Duration::from_millis(100));
+                                   drop(held);
+                               });
+    bme.read_temperature().unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    holder.join().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn iio_backend_should_read_the_kernel_driver_attributes() {
+    let root = std::env::temp_dir().join(format!("bme280-iio-{}", std::process::id()));
+    let other = root.join("iio:device0");
+    let device = root.join("iio:device1");
+    fs::create_dir_all(&other).unwrap();
+    fs::create_dir_all(&device).unwrap();
+    fs::write(other.join("name"), "ads1015\n").unwrap();
+    let attributes = [("name", "bme280\n"),
+                      ("in_temp_input", "25000\n"),
+                      ("in_pressure_input", "101.325000000\n"),
+                      ("in_humidityrelative_input", "42510\n"),
+                      ("in_pressure_oversampling_ratio", "16\n")];
+    for &(attribute, value) in attributes.iter() {
+        fs::write(device.join(attribute), value).unwrap();
+    }
+
+    let iio = IioBme280::find_in(&root).unwrap();
+    assert_eq!(iio.dir(), device.as_path());
+    let measurement = iio.read_measurement().unwrap();
+    assert!((measurement.temperature.unwrap() - 77.0).abs() < 1e-9);
+    assert!((measurement.pressure.unwrap() - 101325.0 * IN_HG_PER_PASCAL).abs() < 1e-9);
+    assert!((measurement.humidity.unwrap() - 42.51).abs() < 1e-9);
+
+    assert_eq!(iio.oversampling(Channel::Pressure).unwrap(), Oversampling::X16);
+    iio.set_oversampling(Channel::Pressure, Oversampling::X4).unwrap();
+    assert_eq!(fs::read_to_string(device.join("in_pressure_oversampling_ratio")).unwrap(), "4");
+    assert!(iio.set_oversampling(Channel::Pressure, Oversampling::Skipped).is_err());
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn should_detect_and_release_kernel_driver_binding() {
+    let root = std::env::temp_dir().join(format!("bme280-driver-{}", std::process::id()));
+    let adapter = root.join("bus/i2c/devices/i2c-1");
+    let driver = root.join("bus/i2c/drivers/bmp280");
+    let device = root.join("bus/i2c/devices/1-0076");
+    fs::create_dir_all(&adapter).unwrap();
+    fs::create_dir_all(&driver).unwrap();
+    fs::create_dir_all(&device).unwrap();
+    std::os::unix::fs::symlink(&driver, device.join("driver")).unwrap();
+    let control = DriverControl::new(&root);
+
+    assert_eq!(control.binding(1, Bme280Address::Primary).unwrap(),
+               Binding::Bound("bmp280".to_string()));
+    assert_eq!(control.binding(1, Bme280Address::Secondary).unwrap(), Binding::Absent);
+
+    control.release(1, Bme280Address::Primary).unwrap();
+    assert_eq!(fs::read_to_string(driver.join("unbind")).unwrap(), "1-0076");
+    control.instantiate(1, Bme280Address::Secondary).unwrap();
+    assert_eq!(fs::read_to_string(adapter.join("new_device")).unwrap(), "bme280 0x77");
+    control.delete(1, Bme280Address::Secondary).unwrap();
+    assert_eq!(fs::read_to_string(adapter.join("delete_device")).unwrap(), "0x77");
+
+    fs::remove_file(device.join("driver")).unwrap();
+    assert_eq!(control.binding(1, Bme280Address::Primary).unwrap(), Binding::Unbound);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn group_should_start_every_conversion_before_reading_any() {
+    let first = RegisterMapDevice::new();
+    let failing = RegisterMapDevice::new();
+    let last = RegisterMapDevice::new();
+    let mut group = SensorGroup::new();
+    // Default-constructed sensors start in normal mode:
+    for dev in [&first, &failing, &last].iter() {
+        group.push(Bme280::new_from_device((*dev).clone()).unwrap()).unwrap();
+    }
+    let written = first.writes().len();
+    failing.fail_next(1);
+
+    let measurement = group.read_measurements();
+
+    assert!(measurement.results[0].is_ok());
+    assert!(measurement.results[1].is_err());
+    assert_eq!(measurement.results[2].as_ref().unwrap().temperature,
+               measurement.results[0].as_ref().unwrap().temperature);
+    // Reading must not trigger a second conversion:
+    let forced = first.writes()[written..]
+        .iter()
+        .filter(|&&(register, value)| {
+                    register == Register::Control as u8 && CtrlMeas::from(value).mode == Mode::Forced
+                })
+        .count();
+    assert_eq!(forced, 1);
+    assert_eq!(CtrlMeas::from(last.get(Register::Control)).mode, Mode::Forced);
+}
+
+/// Sensor returning fixed readings, or failing when it has none.
+struct FixedSensor(Option<(f64, f64, f64)>);
+
+impl FixedSensor {
+    fn read(&self) -> Result<(f64, f64, f64), LinuxI2CError> {
+        self.0.ok_or_else(|| LinuxI2CError::Io(std::io::Error::from_raw_os_error(5)))
+    }
+}
+
+impl Sensor for FixedSensor {
+    fn read_temperature(&self) -> Result<f64, LinuxI2CError> {
+        self.read().map(|values| values.0)
+    }
+    fn read_pressure(&self) -> Result<f64, LinuxI2CError> {
+        self.read().map(|values| values.1)
+    }
+    fn read_humidity(&self) -> Result<f64, LinuxI2CError> {
+        self.read().map(|values| values.2)
+    }
+}
+
+#[test]
+fn should_combine_values_by_method() {
+    let values = [3.0, 1.0, 100.0, 2.0];
+    assert_eq!(combine(FusionMethod::Mean, &values), Some(26.5));
+    assert_eq!(combine(FusionMethod::Median, &values), Some(2.5));
+    assert_eq!(combine(FusionMethod::TrimmedMean(0.25), &values), Some(2.5));
+    assert_eq!(combine(FusionMethod::TrimmedMean(0.5), &[1.0, 2.0, 9.0]), Some(2.0));
+    assert_eq!(combine(FusionMethod::Median, &[]), None);
+}
+
+#[test]
+fn fusion_should_vote_and_flag_the_disagreeing_sensor() {
+    let mut fusion = SensorFusion::new(FusionMethod::Median);
+    fusion.push(FixedSensor(Some((70.0, 29.90, 40.0))));
+    fusion.push(FixedSensor(Some((71.0, 29.91, 42.0))));
+    fusion.push(FixedSensor(Some((80.0, 29.92, 41.0))));
+    fusion.push(FixedSensor(None));
+
+    let fused = fusion.read_fused();
+    let temperature = fused.temperature.unwrap();
+    assert_eq!(temperature.value, 71.0);
+    assert_eq!(temperature.spread, 10.0);
+    assert_eq!(temperature.disagreeing, vec![2]);
+    assert_eq!(temperature.contributors, 3);
+    assert!(fused.humidity.unwrap().disagreeing.is_empty());
+    assert_eq!(fused.errors.len(), 1);
+    assert_eq!(fused.errors[0].0, 3);
+
+    assert_eq!(fusion.read_temperature().unwrap(), 71.0);
+    assert_eq!(fusion.read_measurement().unwrap().pressure, Some(29.91));
+}
+
+#[test]
+fn differential_pressure_should_cancel_the_zeroed_offset() {
+    let upstream = RegisterMapDevice::new();
+    let downstream = RegisterMapDevice::new();
+    downstream.set(Register::PressureData2, 0x00);
+    let mut differential =
+        DifferentialPressure::new(Bme280::new_from_device(upstream.clone()).unwrap(),
+                                  Bme280::new_from_device(downstream.clone()).unwrap())
+            .unwrap();
+    differential.set_filter_window(2);
+
+    let offset = differential.zero(2).unwrap();
+    assert!(offset != 0.0);
+    let first = differential.sample().unwrap();
+    assert!(first.difference.abs() < 1e-6);
+    assert!((first.upstream - first.downstream - offset).abs() < 1e-6);
+
+    // Less pressure behind the filter:
+    downstream.set(Register::PressureData1, 0xE0);
+    let written = (upstream.writes().len(), downstream.writes().len());
+    let second = differential.sample().unwrap();
+    assert!(second.difference > 0.0);
+    assert!((second.filtered - second.difference / 2.0).abs() < 1e-6);
+
+    // Both sensors are triggered exactly once for the sample:
+    let forced = |writes: &[(u8, u8)]| {
+        writes.iter()
+            .filter(|&&(register, value)| {
+                        register == Register::Control as u8 &&
+                        CtrlMeas::from(value).mode == Mode::Forced
+                    })
+            .count()
+    };
+    assert_eq!(forced(&upstream.writes()[written.0..]), 1);
+    assert_eq!(forced(&downstream.writes()[written.1..]), 1);
+}
+
+#[test]
+fn group_should_hold_the_bus_lock_across_the_cycle() {
+    let dir = std::env::temp_dir().join(format!("bme280-group-lock-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let lock = BusLock::new(dir.join("i2c-1.lock"));
+    let mut group = SensorGroup::new();
+    for _ in 0..2 {
+        let mut bme = Bme280::new_from_device(RegisterMapDevice::new()).unwrap();
+        // Both members share one lock, which must only be taken once:
+        bme.set_bus_lock(Some(lock.clone()));
+        group.push(bme).unwrap();
+    }
+
+    let held = lock.acquire().unwrap();
+    let start = Instant::now();
+    let holder = thread::spawn(move || {
+                                   thread::sleep(Duration::from_millis(100));
+                                   drop(held);
+                               });
+    let measurement = group.read_measurements();
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(measurement.results.iter().all(|result| result.is_ok()));
+    holder.join().unwrap();
+
+    // The lock is released again afterwards:
+    drop(lock.acquire().unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}